@@ -20,10 +20,13 @@ export function WebSocketProvider({ wsPort, children }: WebSocketProviderProps)
 
   useEffect(() => {
     const host = window.location.hostname
-    const socket = new WebSocket(`wss://${host}:${wsPort}/ws`)
+    // Same security as the page: wss when served over HTTPS, ws in plain mode
+    const scheme = window.location.protocol === "https:" ? "wss" : "ws"
+    const url = `${scheme}://${host}:${wsPort}/ws`
+    const socket = new WebSocket(url)
     socketRef.current = socket
 
-    console.log(`Connecting to WebSocket at ws://${host}:${wsPort}/ws`)
+    console.log(`Connecting to WebSocket at ${url}`)
 
     socket.onopen = () => setConnected(true)
     socket.onclose = () => {
@@ -5,29 +5,27 @@ import { Label } from "@/components/ui/label"
 import { Button } from "@/components/ui/button"
 import { toast } from "sonner"
 import { useNavigate } from "react-router-dom"
+import { PROTOCOL_VERSION } from "../types/MessageTypes"
 import type { ServerToClientMessage } from "../types/MessageTypes"
 
 
+// Seat QR codes link to /?seat=C4&token=... so the audience doesn't have to type anything.
+// The token is the seat's ticket; the join code may also come in the link.
+const urlParams = new URLSearchParams(window.location.search)
+const seatFromUrl = urlParams.get("seat") ?? ""
+const tokenFromUrl = urlParams.get("token") ?? undefined
+
 export default function LoginPage() {
-  const [seatNumber, setSeatNumber] = useState("")
-  const [maxSeats, setMaxSeats] = useState(64)
+  const [seatNumber, setSeatNumber] = useState(seatFromUrl)
+  const [joinCode, setJoinCode] = useState(urlParams.get("code") ?? "")
+  // Shown once the server asks for a code
+  const [needsCode, setNeedsCode] = useState(false)
   const [loading, setLoading] = useState(false)
 
   const { send, onMessage, connected } = useWebSocket()
 
   const navigate = useNavigate()
 
-  // Still load maxSeats from config.json
-  useEffect(() => {
-    const loadConfig = async () => {
-      const res = await fetch(`/config.json?ts=${Date.now()}`)
-      const config = await res.json()
-      setMaxSeats(config.maxSeats)
-    }
-
-    loadConfig()
-  }, [])
-
   // Attempt to rejoin if session exists in localStorage
   useEffect(() => {
     if (!connected) return
@@ -40,13 +38,14 @@ export default function LoginPage() {
       const expiration = parseInt(expiresAt)
       if (Date.now() < expiration) {
         console.log("Attempting rejoin with ID:", id)
-        send({ type: "rj", id })
-        setSeatNumber(seat)
+        send({ type: "rj", id, protocol: PROTOCOL_VERSION })
+        setSeatNumber(localStorage.getItem("client_seat_label") ?? seat)
         setLoading(true)
       } else {
         console.log("Stored session expired.")
         localStorage.removeItem("client_id")
         localStorage.removeItem("client_seat")
+        localStorage.removeItem("client_seat_label")
         localStorage.removeItem("client_expires_at")
       }
     }
@@ -65,18 +64,36 @@ export default function LoginPage() {
         localStorage.setItem("client_id", msg.id)
         localStorage.setItem("client_expires_at", msg.expiresAt.toString())
         localStorage.setItem("client_seat", msg.seat)
+        localStorage.setItem("client_seat_label", `${msg.row}${msg.column}`)
+        toast.success(`Row ${msg.row}, Seat ${msg.column}`)
 
         sessionStorage.setItem("just_joined", "true")
 
         navigate("/sync")
       } else if (msg.type === "error") {
-        if (msg.message === "Seat is already taken") {
+        if (msg.code === "seat_taken") {
           toast.warning("Seat already taken...")
-        } else if (msg.message === "Client ID is no longer valid") {
+        } else if (msg.code === "invalid_seat") {
+          toast.error(msg.message)
+          localStorage.removeItem("client_id")
+          localStorage.removeItem("client_expires_at")
+          localStorage.removeItem("client_seat")
+          localStorage.removeItem("client_seat_label")
+        } else if (msg.code === "no_free_seat") {
+          toast.warning("Every seat is taken")
+        } else if (msg.code === "protocol_mismatch") {
+          toast.error(msg.message)
+        } else if (msg.code === "join_code_required" || msg.code === "join_code_invalid") {
+          setNeedsCode(true)
+          toast.warning(msg.message)
+        } else if (msg.code === "seat_token_required" || msg.code === "seat_token_invalid") {
+          toast.error(msg.message)
+        } else if (msg.code === "client_id_invalid" || msg.code === "session_not_found") {
           console.warn("Invalid stored session. Clearing localStorage.")
           localStorage.removeItem("client_id")
           localStorage.removeItem("client_expires_at")
           localStorage.removeItem("client_seat")
+          localStorage.removeItem("client_seat_label")
         } else {
           console.warn("Unhandled error message:", msg.message)
         }
@@ -91,19 +108,30 @@ export default function LoginPage() {
   }, [onMessage, connected, navigate])
 
 
-  const isValid = () => {
-    const num = Number(seatNumber)
-    return num >= 1 && num <= maxSeats
+  // The server checks the seat against the session's grid
+  const isValid = () => seatNumber.trim() !== ""
+
+  // "any" has the server pick a free seat
+  const join = (seat: string) => {
+    setLoading(true)
+
+    console.log("Joining seat", seat)
+
+    // The ticket only holds for the seat it was issued for
+    const token = seat === seatFromUrl ? tokenFromUrl : undefined
+    send({
+      type: "j",
+      seat,
+      protocol: PROTOCOL_VERSION,
+      code: joinCode || undefined,
+      token,
+    })
   }
 
   const handleSubmit = (e: React.FormEvent) => {
     e.preventDefault()
     if (!isValid()) return
-    setLoading(true)
-
-    console.log("Joining seat", seatNumber)
-
-    send({ type: "j", seat: Number(seatNumber) })
+    join(seatNumber.trim())
   }
 
   return (
@@ -124,18 +152,29 @@ export default function LoginPage() {
 
         <div className="space-y-4">
           <Label htmlFor="seat" className="text-white">
-            Seat Number
+            Seat
           </Label>
           <Input
             id="seat"
-            type="number"
-            min={1}
-            max={maxSeats}
             value={seatNumber}
-            onChange={(e) => setSeatNumber(e.target.value)}
-            placeholder={`e.g. 8 (1-${maxSeats})`}
+            onChange={(e) => setSeatNumber(e.target.value.toUpperCase())}
+            placeholder="Row and seat, e.g. C4"
             className="bg-black/40 text-white placeholder:text-gray-400"
           />
+          {needsCode && (
+            <>
+              <Label htmlFor="join-code" className="text-white">
+                Join Code
+              </Label>
+              <Input
+                id="join-code"
+                value={joinCode}
+                onChange={(e) => setJoinCode(e.target.value)}
+                placeholder="Shown on the join screen"
+                className="bg-black/40 text-white placeholder:text-gray-400"
+              />
+            </>
+          )}
         </div>
 
         <div className="flex justify-center pt-2">
@@ -147,6 +186,17 @@ export default function LoginPage() {
             {loading ? "Connecting..." : "Connect"}
           </Button>
         </div>
+        <div className="flex justify-center">
+          <Button
+            type="button"
+            variant="link"
+            className="text-gray-300"
+            onClick={() => join("any")}
+            disabled={loading || !connected}
+          >
+            No seat number? Pick one for me
+          </Button>
+        </div>
       </form>
     </div>
   )
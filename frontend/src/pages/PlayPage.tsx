@@ -2,7 +2,7 @@
 import { useEffect, useRef, useState } from "react"
 import { useWebSocket } from "@/context/useWebSocket"
 import { savePatch, saveSheet, loadPatch, loadSheet } from "@/lib/indexedDB";
-import { ReadyMessage, ServerToClientMessage, FileManifestMessage } from "@/types/MessageTypes"
+import { LoadedMessage, ReadyMessage, RefreshMessage, TimeRequestMessage, TimeSampleMessage, ServerToClientMessage, FileManifestMessage, FileRequestMessage, FileChunkHeader, PhaseStartPayload } from "@/types/MessageTypes"
 import { computeHash, concatenate } from "@/lib/utils"
 import { useNavigate } from "react-router-dom"
 
@@ -32,6 +32,9 @@ export default function PlayPage() {
   const timeOffsetRef = useRef<number>(0);
   const schedulerID = useRef<ReturnType<typeof setInterval> | null>(null);
   const lastScheduledBeat = useRef<number>(0);
+  const stopBeatRef = useRef<number>(Infinity);
+  // start_time and seat of the phase we're playing, so a catch-up for it can be ignored
+  const playingRef = useRef<string | null>(null);
 
   function rollingScheduler(sheet: SheetFile, bpm: number, beatZeroAudioMs: number) {
     const ctx = audioCtxRef.current!
@@ -54,7 +57,7 @@ export default function PlayPage() {
 
     for (const track of sheet.tracks) {
       for (const note of track.notes) {
-        if (note.start >= lastScheduledBeat.current && note.start < upperBeat) {
+        if (note.start >= lastScheduledBeat.current && note.start < upperBeat && note.start < stopBeatRef.current) {
           const durBeats = notationToBeats(note.duration)
           sendMIDI(
             note.pitch,
@@ -99,6 +102,17 @@ export default function PlayPage() {
     return () => clearTimeout(t);
   }, [connected, navigate]);
 
+  // Keep our seat lease alive while the page is open
+  useEffect(() => {
+    if (!connected) return
+    const REFRESH_INTERVAL = 60_000
+    const t = setInterval(() => {
+      const id = localStorage.getItem("client_id")
+      if (id) send({ type: "refresh", id } as RefreshMessage)
+    }, REFRESH_INTERVAL)
+    return () => clearInterval(t)
+  }, [connected, send])
+
   // Create & resume the AudioContext on mount
   useEffect(() => {
     const ctx = new (window.AudioContext || window.webkitAudioContext)()
@@ -165,12 +179,12 @@ export default function PlayPage() {
 
       patchMisses.forEach((name) => {
         requestedType.current[name] = "patch";
-        send({ type: "file_request", id: name, fileType: "patch" });
+        send({ type: "file_request", name, fileType: "patch" } as FileRequestMessage);
       });
 
       sheetMisses.forEach((name) => {
         requestedType.current[name] = "sheet";
-        send({ type: "file_request", id: name, fileType: "sheet" });
+        send({ type: "file_request", name, fileType: "sheet" } as FileRequestMessage);
       });
     }
 
@@ -189,11 +203,7 @@ export default function PlayPage() {
         console.log("Header length:", headerLen);
         console.log("Header raw:", new Uint8Array(msg.slice(4, 4 + headerLen)));
         console.log("Header text:", headerText);
-        const { id: name, fileType, isLast } = JSON.parse(headerText) as {
-          id: string
-          isLast: boolean
-          fileType: "patch" | "sheet"
-        }
+        const { id: name, fileType, isLast } = JSON.parse(headerText) as FileChunkHeader
 
         const chunk = msg.slice(4 + headerLen)
 
@@ -233,19 +243,38 @@ export default function PlayPage() {
       } else {
         if (msg.type === "error") {
           console.error("Error from server:", msg.message)
-          if (msg.message === "Client ID is no longer valid") {
+          if (msg.code === "client_id_invalid") {
             console.warn("Invalid stored session. Clearing localStorage.")
             localStorage.removeItem("client_id")
             localStorage.removeItem("client_expires_at")
             localStorage.removeItem("client_seat")
+            localStorage.removeItem("client_seat_label")
             console.log("Redirecting to login page.")
             navigate("/");
           } else {
             console.warn("Unhandled error message:", msg.message)
           }
+        } else if (msg.type === "kicked") {
+          console.warn("Removed from seat:", msg.message)
+          localStorage.removeItem("client_id")
+          localStorage.removeItem("client_expires_at")
+          localStorage.removeItem("client_seat")
+          localStorage.removeItem("client_seat_label")
+          navigate("/");
+        } else if (msg.type === "seat_changed") {
+          // The admin moved us; the new seat has its own files
+          console.log(`Moved to Row ${msg.row}, Seat ${msg.column}`)
+          localStorage.setItem("client_seat", msg.seat)
+          localStorage.setItem("client_seat_label", `${msg.row}${msg.column}`)
+          setReadySent(false)
+          send({ type: "ready", id: localStorage.getItem("client_id") || "" } as ReadyMessage)
         } else if (msg.type === "file_manifest") {
           console.log("Received file manifest:", msg)
+          // The manifest is always for our current seat, even if a move notice was missed
+          localStorage.setItem("client_seat", msg.seat)
           await handleFileManifest(msg)
+        } else if (msg.type === "refreshed") {
+          localStorage.setItem("client_expires_at", msg.expiresAt.toString())
         }
       }
     })
@@ -263,6 +292,14 @@ export default function PlayPage() {
   }, [connected, send, onMessage, navigate])
 
 
+  // Tell the server once every file in the manifest is stored locally and audio can play.
+  // If a phase is already playing, the server answers with a phase_catch_up.
+  useEffect(() => {
+    if (!connected || !audioReady || !readySent || missingPatchFiles.length > 0 || missingSheetFiles.length > 0) return
+    const id = localStorage.getItem("client_id")
+    send({ type: "loaded", id: id || "" } as LoadedMessage)
+  }, [connected, send, audioReady, readySent, missingPatchFiles, missingSheetFiles])
+
   // Wait to listen for phase starts from the server.
   useEffect(() => {
     if (!connected || !audioReady || !readySent || missingPatchFiles.length > 0 || missingSheetFiles.length > 0) {
@@ -283,41 +320,76 @@ export default function PlayPage() {
       }
     }
 
+    // Play our part of a phase from entryBeat on (late joiners come in on a later bar)
+    async function enterPhase(phase: PhaseStartPayload, entryBeat = 0) {
+      const { bpm: newBpm, start_time, count_in, assignments } = phase
+      const seat = localStorage.getItem("client_seat")!
+      const assignment = assignments[seat]
+      if (!assignment) return
+      const { rnbo_id, sheet_id } = assignment
+
+      // Load patch & sheet
+      const patchBlob = await loadPatch(rnbo_id); if (!patchBlob) return
+      const sheetBlob = await loadSheet(sheet_id); if (!sheetBlob) return
+      const patchJSON = patchBlob as unknown as IPatcher
+      const sheetJSON = sheetBlob as unknown as SheetFile
+
+      // convert wall-clock start_time to audio clock ms
+      const ctx = audioCtxRef.current!
+      const audioNowMs = ctx.currentTime * 1000
+      const wallNowMs = Date.now() + timeOffsetRef.current // server clock
+      // audio-based beat zero time
+      const startAudioMs = audioNowMs + (start_time - wallNowMs) + count_in * ((60*1000)/newBpm)
+      beatZeroRef.current = startAudioMs
+
+      setBpm(newBpm)
+      lastScheduledBeat.current = entryBeat
+      stopBeatRef.current = Infinity
+      playingRef.current = `${start_time}:${seat}`
+
+      await setupDevice(ctx, patchJSON)
+      startScheduler(sheetJSON)
+    }
+
     const unsub = onMessage<ServerToClientMessage>(async (msg) => {
       if (msg instanceof Blob || msg instanceof ArrayBuffer) return;
       if (msg.type === "phase_start") {
         console.log("Received phase start message:", msg)
-        const { bpm: newBpm, start_time, count_in, assignments } = msg
-        const seat = localStorage.getItem("client_seat")!
-        if (!assignments[seat]) return
-        const { rnbo_id, sheet_id } = assignments[seat]
-
-        // Load patch & sheet
-        const patchBlob = await loadPatch(rnbo_id); if (!patchBlob) return
-        const sheetBlob = await loadSheet(sheet_id); if (!sheetBlob) return
-        const patchJSON = patchBlob as unknown as IPatcher
-        const sheetJSON = sheetBlob as unknown as SheetFile
-
-        // convert wall-clock start_time to audio clock ms
-        const ctx = audioCtxRef.current!
-        const audioNowMs = ctx.currentTime * 1000
-        const wallNowMs = Date.now()
-        // audio-based beat zero time
-        const startAudioMs = audioNowMs + (start_time - wallNowMs) + count_in * ((60*1000)/newBpm)
-        beatZeroRef.current = startAudioMs
-
-        setBpm(newBpm)
-        lastScheduledBeat.current = 0
-
-        await setupDevice(ctx, patchJSON)
-        startScheduler(sheetJSON)
+        await enterPhase(msg)
+      } else if (msg.type === "phase_catch_up") {
+        const seat = localStorage.getItem("client_seat")
+        if (schedulerID.current !== null && playingRef.current === `${msg.start_time}:${seat}`) return
+        console.log(`Catching up at beat ${msg.current_beat.toFixed(1)}, entering on beat ${msg.entry_beat}`)
+        stopScheduler()
+        await enterPhase(msg, msg.entry_beat)
+      } else if (msg.type === "refreshed") {
+        localStorage.setItem("client_expires_at", msg.expiresAt.toString())
+      } else if (msg.type === "offset_update") {
+        // Apply the server's drift-corrected offset, then give it a fresh sample
+        if (msg.offset_ms !== null) {
+          timeOffsetRef.current = msg.offset_ms
+          localStorage.setItem("offset", msg.offset_ms.toString())
+        }
+        send({ type: "time_request", client_time: Date.now() } as TimeRequestMessage)
+      } else if (msg.type === "tq_result") {
+        send({
+          type: "time_sample",
+          client_time: msg.client_time,
+          server_time: msg.server_time,
+          receive_time: Date.now(),
+        } as TimeSampleMessage)
       } else if (msg.type === "phase_stop") {
-        stopScheduler();
+        // Stop scheduling notes past the stop beat, then stop the scheduler once it arrives
+        const ctx = audioCtxRef.current!
+        const delayMs = Math.max(0, msg.stop_time - (Date.now() + timeOffsetRef.current))
+        const stopAudioMs = ctx.currentTime * 1000 + delayMs
+        stopBeatRef.current = (stopAudioMs - beatZeroRef.current) / ((60 * 1000) / bpm)
+        setTimeout(stopScheduler, delayMs);
       }
     });
 
     return unsub;
-  }, [connected, onMessage, audioReady, readySent, missingPatchFiles, missingSheetFiles, bpm]);
+  }, [connected, send, onMessage, audioReady, readySent, missingPatchFiles, missingSheetFiles, bpm]);
 
 
   return (
@@ -4,6 +4,7 @@ import { useWebSocket } from "@/context/useWebSocket"
 import { useNavigate } from "react-router-dom"
 import type {
   TimeRequestMessage,
+  TimeSampleMessage,
   TimeSyncMessage,
   // RecordMessage,
   // RejoinRequestMessage,
@@ -143,6 +144,12 @@ export default function SyncPage() {
       console.debug(`[SyncPage] RTT sample: ${rtt} ms`);
 
       rttsRef.current.push(rtt); // Store RTT sample
+      send({
+        type: "time_sample",
+        client_time: msg.client_time,
+        server_time: msg.server_time,
+        receive_time: now,
+      } as TimeSampleMessage);
 
       const m = Math.min(...rttsRef.current);
       setMinRtt(m);
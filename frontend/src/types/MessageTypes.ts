@@ -1,22 +1,30 @@
 // MessageTypes.ts
+//
+// The message shapes are generated from the server's protocol.rs into ./generated
+// (regenerate with `cargo run -p rtpc-core --bin export-bindings`; the
+// rtpc-core test tests/bindings.rs fails when they are out of date). This file
+// only gives the individual messages names.
 
-// Joining
-export type JoinRequestMessage = {
-    type: "j";
-    seat: number;
-};
+import type { ClientMessage } from "./generated/ClientMessage";
+import type { ServerMessage } from "./generated/ServerMessage";
 
-export type RejoinRequestMessage = {
-    type: "rj";
-    id: string;
-};
+export { PROTOCOL_VERSION } from "./generated/protocol";
+export type { ClientMessage, ServerMessage };
+export type { ErrorCode } from "./generated/ErrorCode";
+export type { FileType } from "./generated/FileType";
+export type { ManifestEntry } from "./generated/ManifestEntry";
 
-export type JoinedMessage = {
-    type: "joined";
-    id: string;
-    seat: string;
-    expiresAt: number;
-};
+type ClientMsg<T extends ClientMessage["type"]> = Extract<ClientMessage, { type: T }>;
+type ServerMsg<T extends ServerMessage["type"]> = Extract<ServerMessage, { type: T }>;
+
+// Joining
+export type JoinRequestMessage = ClientMsg<"j">;
+export type RejoinRequestMessage = ClientMsg<"rj">;
+export type JoinedMessage = ServerMsg<"joined">;
+
+// Lease renewal
+export type RefreshMessage = ClientMsg<"refresh">;
+export type RefreshedMessage = ServerMsg<"refreshed">;
 
 
 // Acoustic Beacon - Not in use
@@ -29,88 +37,42 @@ export type RecordMessage = {
     beacon_id: string;
 };
 
-// export type OffsetMessage = {
-//     type: "offset";
-//     offset: number;
-//     client_id: string;
-//     sample_offset: number;
-// };
-
 // Time Sync
-export type TimeSyncMessage = {
-    type: "tq_result";
-    client_time: number;
-    server_time: number;
-}
+export type TimeSyncMessage = ServerMsg<"tq_result">;
+export type TimeRequestMessage = ClientMsg<"time_request">;
 
-export type TimeRequestMessage = {
-    type: "time_request";
-    client_time: number;
-}
+// Sent back after each tq_result so the server can track our sync quality
+export type TimeSampleMessage = ClientMsg<"time_sample">;
 
-// Performance
-export type ReadyMessage = {
-    type: "ready";
-    id: string;
-};
+// Drift-corrected offset pushed by the server during a performance.
+// Clients answer with a fresh time_request.
+export type OffsetUpdateMessage = ServerMsg<"offset_update">;
 
-export type FileManifestMessage = {
-    type: "file_manifest";
-    seat: string;
-    patch_files: { name: string; size: number; hash: string }[];
-    sheet_files: { name: string; size: number; hash: string }[];
-};
+// Performance
+export type ReadyMessage = ClientMsg<"ready">;
+export type FileManifestMessage = ServerMsg<"file_manifest">;
+export type LoadedMessage = ClientMsg<"loaded">;
 
-export type FileRequestMessage = {
-    type: "file_request";
-    name: string;
-    fileType: "patch" | "sheet";
-};
+// Sent when the admin moves us to another seat or removes us
+export type SeatChangedMessage = ServerMsg<"seat_changed">;
+export type KickedMessage = ServerMsg<"kicked">;
+export type FileRequestMessage = ClientMsg<"file_request">;
 
-export type PhaseStartMessage = {
-    type: "phase_start";
-    bpm: number;
-    count_in: number;
-    start_time: number; // UNIX ms timestamp
-    assignments: Record<string, {
-        rnbo_id: string;
-        sheet_id: string;
-    }>;
-};
+// JSON header at the front of each binary file chunk
+export type FileChunkHeader = ServerMsg<"file_chunk">;
 
-export type PhaseStopMessage = {
-    type: "phase_stop";
-}
+export type PhaseStartMessage = ServerMsg<"phase_start">; // start_time is server ms
+export type PhaseStopMessage = ServerMsg<"phase_stop">; // stop_time is on a beat boundary
+// The phase already playing when we finished loading; come in on entry_beat
+export type PhaseCatchUpMessage = ServerMsg<"phase_catch_up">;
+export type { PhaseStartPayload } from "./generated/PhaseStartPayload";
 
 // Error and misc.
-export type ErrorMessage = {
-    type: "error";
-    message: string;
-};
-
-export type PingMessage = {
-    type: "ping";
-};
-
-export type PongMessage = {
-    type: "pong";
-};
+export type ErrorMessage = ServerMsg<"error">;
+export type PingMessage = ClientMsg<"ping">;
+export type PongMessage = ServerMsg<"pong">;
 
 
 // Message types for the server to client and client to server messages
-export type ServerToClientMessage =
-    | TimeSyncMessage
-    | JoinedMessage
-    | RecordMessage
-    | ErrorMessage
-    | PongMessage
-    | FileManifestMessage
-    | PhaseStartMessage
-    | PhaseStopMessage;
-
-export type ClientToServerMessage =
-    | TimeRequestMessage
-    | JoinRequestMessage
-    | RejoinRequestMessage
-    | PingMessage
-    | ReadyMessage;
+export type ServerToClientMessage = ServerMessage | RecordMessage;
+export type ClientToServerMessage = ClientMessage;
@@ -1,18 +1,4 @@
-export interface SheetNote {
-    pitch: number;
-    velocity: number;
-    start: number;
-    duration: string; // e.g. "4n", "8t", etc.
-}
-
-export interface SheetTrack {
-    instrument: string;
-    channel: number;
-    notes: SheetNote[];
-}
-
-export interface SheetFile {
-    bpm: number;
-    end_beat: number;
-    tracks: SheetTrack[];
-}
\ No newline at end of file
+// Sheet music format, generated from the Rust definitions in state.rs
+export type { SheetFile } from "./generated/SheetFile";
+export type { SheetTrack } from "./generated/SheetTrack";
+export type { SheetNote } from "./generated/SheetNote";
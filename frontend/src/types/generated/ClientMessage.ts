@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FileType } from "./FileType";
+import type { SeatValue } from "./SeatValue";
+
+export type ClientMessage = { "type": "ping" } | { "type": "j", seat: SeatValue, protocol?: number, 
+/**
+ * Session join code, if the server asks for one
+ */
+code?: string, 
+/**
+ * Seat ticket from a seat's QR code
+ */
+token?: string, } | { "type": "rj", id: string, protocol?: number, } | { "type": "refresh", id: string, } | { "type": "time_request", client_time: number, } | { "type": "time_sample", client_time: number, server_time: number, receive_time: number, } | { "type": "ready", id: string, } | { "type": "file_request", name: string, fileType: FileType, } | { "type": "loaded", id: string, };
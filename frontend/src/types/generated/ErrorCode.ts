@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ErrorCode = "invalid_json" | "unknown_message" | "invalid_message" | "protocol_mismatch" | "invalid_seat" | "seat_taken" | "no_free_seat" | "join_code_required" | "join_code_invalid" | "seat_token_required" | "seat_token_invalid" | "session_not_found" | "client_id_invalid" | "file_not_found" | "slow_connection";
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AssignmentPayload } from "./AssignmentPayload";
+
+/**
+ * A phase that is already playing, for a client that joined or reconnected after it started
+ */
+export type PhaseCatchUpPayload = { 
+/**
+ * Beats since the downbeat at `server_time`; negative during the count-in
+ */
+current_beat: number, server_time: number, 
+/**
+ * Where the client comes in: the first bar line it can still reach in time
+ */
+entry_beat: number, entry_time: number, bpm: number, count_in: number, start_time: number, assignments: { [key in string]?: AssignmentPayload }, };
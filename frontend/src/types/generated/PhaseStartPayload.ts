@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AssignmentPayload } from "./AssignmentPayload";
+
+export type PhaseStartPayload = { bpm: number, count_in: number, start_time: number, assignments: { [key in string]?: AssignmentPayload }, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Seat numbers arrive either as text or as a JSON number
+ */
+export type SeatValue = string | number;
@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ErrorCode } from "./ErrorCode";
+import type { FileType } from "./FileType";
+import type { ManifestEntry } from "./ManifestEntry";
+import type { PhaseCatchUpPayload } from "./PhaseCatchUpPayload";
+import type { PhaseStartPayload } from "./PhaseStartPayload";
+import type { PhaseStopPayload } from "./PhaseStopPayload";
+
+export type ServerMessage = { "type": "pong" } | { "type": "joined", id: string, 
+/**
+ * Seat index, the key used in phase assignments
+ */
+seat: string, 
+/**
+ * Row letter, A for the first row
+ */
+row: string, 
+/**
+ * Seat number within the row, from 1
+ */
+column: number, expiresAt: number, protocol: number, } | { "type": "refreshed", expiresAt: number, } | { "type": "tq_result", client_time: number, server_time: number, } | { "type": "offset_update", offset_ms: number | null, skew_ppm: number | null, server_time: number, } | { "type": "seat_changed", seat: string, row: string, column: number, } | { "type": "kicked", message: string, } | { "type": "file_manifest", seat: string, patch_files: Array<ManifestEntry>, sheet_files: Array<ManifestEntry>, } | { "type": "file_chunk", id: string, fileType: FileType, isLast: boolean, } | { "type": "phase_start" } & PhaseStartPayload | { "type": "phase_catch_up" } & PhaseCatchUpPayload | { "type": "phase_stop" } & PhaseStopPayload | { "type": "error", code: ErrorCode, message: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SheetTrack } from "./SheetTrack";
+
+/**
+ * A sheet music file as read by the client. The backend only stores and serves these.
+ */
+export type SheetFile = { bpm: number, end_beat: number, tracks: Array<SheetTrack>, };
@@ -0,0 +1,3 @@
+// Generated from PROTOCOL_VERSION in protocol.rs. Do not edit by hand.
+
+export const PROTOCOL_VERSION = 6;
@@ -0,0 +1,16 @@
+//! Regenerates the TypeScript definitions and JSON Schemas from the Rust types. Run it after
+//! changing anything that crosses the wire or lands on disk, and commit the result.
+
+use std::path::PathBuf;
+
+use rtpc_core::bindings;
+
+fn main() {
+    // The checkout this was built from, unless another root is given
+    let root = std::env::args().nth(1).map(PathBuf::from).unwrap_or_else(bindings::repo_root);
+    if let Err(e) = bindings::export_all(&root) {
+        eprintln!("[bindings] {}", e);
+        std::process::exit(1);
+    }
+    println!("[bindings] Exported to {}", root.display());
+}
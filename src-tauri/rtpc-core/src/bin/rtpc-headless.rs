@@ -0,0 +1,465 @@
+//! Runs a saved session without the Tauri window, e.g. on a box next to the venue's network rack.
+//!
+//! Phases are started and stopped with line commands on stdin, or on an optional TCP control
+//! socket (one command per line, one reply line each) for scripting. The socket has no
+//! authentication, so it only listens on loopback unless `--control-remote` says otherwise.
+//! Broker events are printed to stdout as `[event] <name> <json>`.
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{watch, Mutex};
+
+use rtpc_core::events::StdoutEvents;
+use rtpc_core::journal;
+use rtpc_core::network::list_interfaces;
+use rtpc_core::outbox::SlowClientPolicy;
+use rtpc_core::seats::SeatAllocation;
+use rtpc_core::server_controller::ServerController;
+use rtpc_core::state::AppState;
+use rtpc_core::tls::TlsSource;
+use rtpc_core::{
+    Error, ServerOptions, Transport, DEFAULT_RESYNC_INTERVAL_MS, DEFAULT_SAFETY_MS,
+};
+
+const USAGE: &str = "\
+Usage: rtpc-headless <session.json> [options]
+
+Options:
+  --port <port>         WSS/HTTPS port, or WS/HTTP with --transport plain (default 443)
+  --transport <mode>    tls, plain, or both (default tls)
+  --plain-port <port>   WS/HTTP port with --transport both
+  --redirect-port <port>  redirect plain HTTP on this port to HTTPS
+  --bind <ip>           listen on one interface only (IPv4 or IPv6; default all IPv4)
+  --advertise <ip>      address announced to clients (default the bound or main LAN address)
+  --interfaces          list network interfaces and exit
+  --hostname <label>    advertise over mDNS as <label>.local (default rtpc)
+  --no-mdns             don't advertise over mDNS
+  --join-by-hostname    put the .local hostname in join links instead of the IP
+  --join-code <code>    code the audience enters to take a seat
+  --seat-tokens         only admit holders of a seat's QR ticket (see `sheet`)
+  --seat-allocation <strategy>  seat for clients joining with `any`: in-order, spread,
+                        random or assigned (default in-order)
+  --seat-seed <n>       seed for random seat allocation
+  --slow-clients <policy>  clients that fall behind: disconnect, pause-files or warn
+                        (default disconnect)
+  --ttl-ms <ms>         client lease length (default 3600000)
+  --safety-ms <ms>      margin added to the worst RTT when scheduling (default 250)
+  --resync-ms <ms>      interval between clock offset updates (default 30000)
+  --control <addr>      also accept commands on a TCP socket, given as a port on
+                        127.0.0.1 (e.g. 7070) or a full address
+  --control-remote      allow a control address other than loopback; anyone who
+                        can reach it controls the show
+  --cert <file>         TLS certificate (PEM); needs --key
+  --key <file>          TLS private key (PEM)
+  --cert-dir <dir>      where self-signed certificates are kept (default certs)
+
+Commands:
+  phases                list the session's phases
+  start <id or name>    start a phase on every client
+  stop                  stop the current phase on the next beat
+  clients               print the client roster as JSON
+  kick <seat>           remove the client in a seat
+  move <seat> <seat>    move the client in the first seat to the empty second one
+  swap <seat> <seat>    exchange the clients in two seats
+  qr [seat]             print the join link, optionally for one seat, e.g. C4
+  sheet <file.html>     write a printable page of per-seat QR codes
+  quit                  stop the server and exit";
+
+struct Options {
+    session: String,
+    port: u16,
+    transport: Transport,
+    plain_port: Option<u16>,
+    redirect_port: Option<u16>,
+    bind: Option<String>,
+    advertise: Option<String>,
+    mdns: bool,
+    hostname: Option<String>,
+    join_by_hostname: bool,
+    join_code: Option<String>,
+    seat_tokens: bool,
+    seat_allocation: SeatAllocation,
+    seat_seed: Option<u64>,
+    slow_clients: SlowClientPolicy,
+    ttl_ms: u64,
+    safety_ms: u64,
+    resync_ms: u64,
+    control: Option<SocketAddr>,
+    control_remote: bool,
+    tls: TlsSource,
+}
+
+type SharedController = Arc<Mutex<ServerController>>;
+
+/// What the caller should do after a command
+enum Reply {
+    Text(String),
+    Quit,
+}
+
+#[tokio::main]
+async fn main() {
+    let opts = match parse_args(std::env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(opts).await {
+        eprintln!("[headless] {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut opts = Options {
+        session: String::new(),
+        port: 443,
+        transport: Transport::Tls,
+        plain_port: None,
+        redirect_port: None,
+        bind: None,
+        advertise: None,
+        mdns: true,
+        hostname: None,
+        join_by_hostname: false,
+        join_code: None,
+        seat_tokens: false,
+        seat_allocation: SeatAllocation::InOrder,
+        seat_seed: None,
+        slow_clients: SlowClientPolicy::Disconnect,
+        ttl_ms: 3_600_000,
+        safety_ms: DEFAULT_SAFETY_MS,
+        resync_ms: DEFAULT_RESYNC_INTERVAL_MS,
+        control: None,
+        control_remote: false,
+        tls: TlsSource {
+            generated_dir: PathBuf::from("certs"),
+            ..Default::default()
+        },
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--port" => opts.port = parse_value(&arg, value()?)?,
+            "--transport" => opts.transport = value()?.parse()?,
+            "--plain-port" => opts.plain_port = Some(parse_value(&arg, value()?)?),
+            "--redirect-port" => opts.redirect_port = Some(parse_value(&arg, value()?)?),
+            "--bind" => opts.bind = Some(value()?),
+            "--advertise" => opts.advertise = Some(value()?),
+            "--hostname" => opts.hostname = Some(value()?),
+            "--no-mdns" => opts.mdns = false,
+            "--join-by-hostname" => opts.join_by_hostname = true,
+            "--join-code" => opts.join_code = Some(value()?),
+            "--seat-tokens" => opts.seat_tokens = true,
+            "--seat-allocation" => opts.seat_allocation = value()?.parse()?,
+            "--seat-seed" => opts.seat_seed = Some(parse_value(&arg, value()?)?),
+            "--slow-clients" => opts.slow_clients = value()?.parse()?,
+            "--interfaces" => {
+                print_interfaces();
+                std::process::exit(0);
+            }
+            "--ttl-ms" => opts.ttl_ms = parse_value(&arg, value()?)?,
+            "--safety-ms" => opts.safety_ms = parse_value(&arg, value()?)?,
+            "--resync-ms" => opts.resync_ms = parse_value(&arg, value()?)?,
+            "--control" => opts.control = Some(parse_control(&arg, value()?)?),
+            "--control-remote" => opts.control_remote = true,
+            "--cert" => opts.tls.cert_path = Some(value()?.into()),
+            "--key" => opts.tls.key_path = Some(value()?.into()),
+            "--cert-dir" => opts.tls.generated_dir = value()?.into(),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ if opts.session.is_empty() => opts.session = arg,
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+
+    if opts.session.is_empty() {
+        return Err("Missing session file".into());
+    }
+    if let Some(addr) = opts.control.filter(|addr| !addr.ip().is_loopback()) {
+        if !opts.control_remote {
+            return Err(format!(
+                "The control socket has no authentication; pass --control-remote to listen on {}",
+                addr
+            ));
+        }
+    }
+    Ok(opts)
+}
+
+/// A bare port listens on loopback only
+fn parse_control(name: &str, value: String) -> Result<SocketAddr, String> {
+    match value.parse::<u16>() {
+        Ok(port) => Ok(SocketAddr::from((Ipv4Addr::LOCALHOST, port))),
+        Err(_) => parse_value(name, value),
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+async fn run(opts: Options) -> rtpc_core::Result<()> {
+    let options = server_options(&opts);
+    let listen = options.listen()?;
+    let app_state = Arc::new(AppState::default());
+    app_state.load_from_file(&opts.session).await?;
+
+    let mut ctrl = ServerController::new(
+        opts.ttl_ms,
+        opts.safety_ms,
+        Duration::from_millis(opts.resync_ms.max(1)),
+        app_state,
+        Arc::new(StdoutEvents),
+    );
+    let seat_secret = ctrl.app_state.seat_secret().await;
+    ctrl.set_join_access(options.join_access(seat_secret.as_deref())).await;
+    ctrl.set_seat_allocator(options.seat_allocator()).await;
+    ctrl.set_slow_client_policy(options.slow_clients.unwrap_or_default()).await;
+    ctrl.set_journal_path(journal::path_for(&opts.session));
+    let info = ctrl.start(&listen, &opts.tls).await?;
+    println!("[headless] clients join at {}", info.join_url);
+    if let Some(tls_info) = info.tls.filter(|tls| tls.self_signed) {
+        println!("[headless] self-signed certificate, audiences will see a warning for SHA-256 {}", tls_info.fingerprint);
+    }
+    let ctrl: SharedController = Arc::new(Mutex::new(ctrl));
+
+    let (quit_tx, mut quit_rx) = watch::channel(false);
+
+    if let Some(addr) = opts.control {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| Error::io(format!("Failed to bind control socket {}", addr), e))?;
+        println!("[headless] control socket on {}", addr);
+        tokio::spawn(serve_control(listener, ctrl.clone(), quit_tx.clone()));
+    }
+
+    // Without a control socket, closing stdin ends the show
+    let stdin_quits = opts.control.is_none();
+    tokio::spawn(read_stdin(ctrl.clone(), quit_tx, stdin_quits));
+
+    tokio::select! {
+        _ = quit_rx.wait_for(|quit| *quit) => {}
+        _ = tokio::signal::ctrl_c() => println!("[headless] interrupted"),
+    }
+
+    ctrl.lock().await.stop().await;
+    Ok(())
+}
+
+fn print_interfaces() {
+    match list_interfaces() {
+        Ok(interfaces) => {
+            for interface in interfaces {
+                println!("{}\t{}", interface.name, interface.address);
+            }
+        }
+        Err(e) => eprintln!("[headless] {}", e),
+    }
+}
+
+/// The flags as the server options the app would send
+fn server_options(opts: &Options) -> ServerOptions {
+    ServerOptions {
+        ws_port: opts.port,
+        ttl_ms: opts.ttl_ms,
+        safety_ms: Some(opts.safety_ms),
+        resync_interval_ms: Some(opts.resync_ms),
+        cert_path: None,
+        key_path: None,
+        transport: opts.transport,
+        plain_port: opts.plain_port,
+        redirect_port: opts.redirect_port,
+        bind_address: opts.bind.clone(),
+        advertise_address: opts.advertise.clone(),
+        mdns: Some(opts.mdns),
+        mdns_hostname: opts.hostname.clone(),
+        join_by_hostname: Some(opts.join_by_hostname),
+        join_code: opts.join_code.clone(),
+        require_seat_tokens: Some(opts.seat_tokens),
+        seat_allocation: Some(opts.seat_allocation),
+        seat_seed: opts.seat_seed,
+        slow_clients: Some(opts.slow_clients),
+    }
+}
+
+async fn read_stdin(ctrl: SharedController, quit: watch::Sender<bool>, quit_on_eof: bool) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        match execute(&ctrl, &line).await {
+            Reply::Text(text) if !text.is_empty() => println!("{}", text),
+            Reply::Text(_) => {}
+            Reply::Quit => {
+                quit.send(true).ok();
+                return;
+            }
+        }
+    }
+    if quit_on_eof {
+        quit.send(true).ok();
+    }
+}
+
+async fn serve_control(listener: TcpListener, ctrl: SharedController, quit: watch::Sender<bool>) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("[control] accept failed: {}", e);
+                continue;
+            }
+        };
+        println!("[control] {} connected", peer);
+
+        let ctrl = ctrl.clone();
+        let quit = quit.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let text = match execute(&ctrl, &line).await {
+                    Reply::Text(text) => text,
+                    Reply::Quit => {
+                        let _ = writer.write_all(b"bye\n").await;
+                        quit.send(true).ok();
+                        return;
+                    }
+                };
+                if writer.write_all(format!("{}\n", text).as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            println!("[control] {} disconnected", peer);
+        });
+    }
+}
+
+/// Run one command line and describe the result
+async fn execute(ctrl: &SharedController, line: &str) -> Reply {
+    let line = line.trim();
+    let (cmd, arg) = match line.split_once(char::is_whitespace) {
+        Some((cmd, arg)) => (cmd, arg.trim()),
+        None => (line, ""),
+    };
+
+    let result = match cmd {
+        "" => Ok(String::new()),
+        "phases" => Ok(list_phases(ctrl).await),
+        "start" => start_phase(ctrl, arg).await,
+        "stop" => {
+            let stop_time = ctrl.lock().await.stop_phase().await;
+            Ok(format!("stopping at {}", stop_time))
+        }
+        "clients" => {
+            let clients = ctrl.lock().await.perf_state.lock().await.client_summaries();
+            serde_json::to_string(&clients).map_err(|e| e.to_string())
+        }
+        "kick" => kick(ctrl, arg).await,
+        "move" | "swap" => reseat(ctrl, cmd, arg).await,
+        "qr" => join_link(ctrl, arg).await,
+        "sheet" => export_sheet(ctrl, arg).await,
+        "help" => Ok(USAGE.to_string()),
+        "quit" | "exit" => return Reply::Quit,
+        _ => Err(format!("Unknown command `{}` (try `help`)", cmd)),
+    };
+
+    Reply::Text(result.unwrap_or_else(|e| format!("error: {}", e)))
+}
+
+async fn list_phases(ctrl: &SharedController) -> String {
+    let app_state = ctrl.lock().await.app_state.clone();
+    let phases = app_state.phases.lock().await;
+
+    let mut sorted: Vec<_> = phases.iter().collect();
+    sorted.sort_by_key(|(_, phase)| phase.index);
+    sorted
+        .into_iter()
+        .map(|(id, phase)| format!("{}\t{}\t{} bpm", id, phase.name, phase.bpm))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Start a phase by ID, or by name if no ID matches
+async fn start_phase(ctrl: &SharedController, wanted: &str) -> Result<String, String> {
+    if wanted.is_empty() {
+        return Err("Usage: start <id or name>".into());
+    }
+
+    let ctrl = ctrl.lock().await;
+    let phase_id = {
+        let phases = ctrl.app_state.phases.lock().await;
+        if phases.contains_key(wanted) {
+            wanted.to_string()
+        } else {
+            phases
+                .iter()
+                .find(|(_, phase)| phase.name.eq_ignore_ascii_case(wanted))
+                .map(|(id, _)| id.clone())
+                .ok_or_else(|| format!("Phase `{}` not found", wanted))?
+        }
+    };
+
+    let lead_ms = ctrl.start_phase(phase_id.clone()).await.map_err(|e| e.to_string())?;
+    Ok(format!("started {} in {} ms", phase_id, lead_ms))
+}
+
+async fn kick(ctrl: &SharedController, seat: &str) -> Result<String, String> {
+    if seat.is_empty() {
+        return Err("Usage: kick <seat>".into());
+    }
+    let ctrl = ctrl.lock().await;
+    let client_id = ctrl.client_at(seat).await.map_err(|e| e.to_string())?;
+    ctrl.kick_client(&client_id).await.map_err(|e| e.to_string())?;
+    Ok(format!("kicked {}", client_id))
+}
+
+/// `move` or `swap` between two seats
+async fn reseat(ctrl: &SharedController, cmd: &str, args: &str) -> Result<String, String> {
+    let Some((from, to)) = args.split_once(char::is_whitespace) else {
+        return Err(format!("Usage: {} <seat> <seat>", cmd));
+    };
+    let (from, to) = (from.trim(), to.trim());
+
+    let ctrl = ctrl.lock().await;
+    if cmd == "move" {
+        let client_id = ctrl.client_at(from).await.map_err(|e| e.to_string())?;
+        ctrl.move_client(&client_id, to).await.map_err(|e| e.to_string())?;
+        Ok(format!("moved {} to {}", client_id, to))
+    } else {
+        ctrl.swap_seats(from, to).await.map_err(|e| e.to_string())?;
+        Ok(format!("swapped {} and {}", from, to))
+    }
+}
+
+/// Join link, for all seats or just `seat`
+async fn join_link(ctrl: &SharedController, seat: &str) -> Result<String, String> {
+    let seat = Some(seat).filter(|seat| !seat.is_empty());
+    let qr = ctrl.lock().await.join_qr(seat, true).await.map_err(|e| e.to_string())?;
+    Ok(qr.url)
+}
+
+async fn export_sheet(ctrl: &SharedController, path: &str) -> Result<String, String> {
+    if path.is_empty() {
+        return Err("Usage: sheet <file.html>".into());
+    }
+    ctrl.lock()
+        .await
+        .export_seat_sheet(Path::new(path))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(format!("wrote {}", path))
+}
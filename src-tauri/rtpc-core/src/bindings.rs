@@ -0,0 +1,88 @@
+//! TypeScript and JSON Schema export for everything that crosses the wire or
+//! lands on disk. The generated files are committed: regenerate them with
+//! `cargo run -p rtpc-core --bin export-bindings`, and the `bindings` test fails
+//! when they no longer match the Rust definitions.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use schemars::{schema_for, JsonSchema};
+use ts_rs::TS;
+
+use crate::error::{Error, Result};
+use crate::network::NetworkInterface;
+use crate::performance_types::ClientSummary;
+use crate::qr::JoinQr;
+use crate::protocol::{ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use crate::tls::TlsInfo;
+use crate::server_controller::{ServerInfo, ServerStatus};
+use crate::ServerOptions;
+use crate::state::{AppStateSnapshot, PhaseInit, PhaseUpdate, SessionSaveState, SheetFile};
+
+const CLIENT_DIR: &str = "frontend/src/types/generated";
+const ADMIN_DIR: &str = "src/types/generated";
+const SCHEMA_DIR: &str = "schemas";
+
+/// Every directory [`export_all`] writes to, relative to the repo root
+pub const GENERATED_DIRS: [&str; 3] = [CLIENT_DIR, ADMIN_DIR, SCHEMA_DIR];
+
+/// The repo root of this checkout, where the generated files are committed
+pub fn repo_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+/// Writes all bindings into the client, the admin UI and `schemas/` under `root`
+pub fn export_all(root: &Path) -> Result<()> {
+    // Client (frontend/): websocket protocol and sheet format
+    let client_dir = root.join(CLIENT_DIR);
+    export_ts::<ClientMessage>(&client_dir)?;
+    export_ts::<ServerMessage>(&client_dir)?;
+    export_ts::<SheetFile>(&client_dir)?;
+    write(
+        &client_dir.join("protocol.ts"),
+        &format!(
+            "// Generated from PROTOCOL_VERSION in protocol.rs. Do not edit by hand.\n\nexport const PROTOCOL_VERSION = {};\n",
+            PROTOCOL_VERSION
+        ),
+    )?;
+
+    // Admin (src/): session file and command payloads
+    let admin_dir = root.join(ADMIN_DIR);
+    export_ts::<SessionSaveState>(&admin_dir)?;
+    export_ts::<PhaseInit>(&admin_dir)?;
+    export_ts::<PhaseUpdate>(&admin_dir)?;
+    export_ts::<AppStateSnapshot>(&admin_dir)?;
+    export_ts::<ClientSummary>(&admin_dir)?;
+    export_ts::<ServerOptions>(&admin_dir)?;
+    export_ts::<TlsInfo>(&admin_dir)?;
+    export_ts::<ServerInfo>(&admin_dir)?;
+    export_ts::<ServerStatus>(&admin_dir)?;
+    export_ts::<NetworkInterface>(&admin_dir)?;
+    export_ts::<JoinQr>(&admin_dir)?;
+
+    // JSON Schemas for third-party tools
+    let schema_dir = root.join(SCHEMA_DIR);
+    export_schema::<ClientMessage>(&schema_dir, "client-message")?;
+    export_schema::<ServerMessage>(&schema_dir, "server-message")?;
+    export_schema::<SessionSaveState>(&schema_dir, "session")?;
+    export_schema::<SheetFile>(&schema_dir, "sheet")?;
+
+    Ok(())
+}
+
+fn export_ts<T: TS + 'static>(dir: &Path) -> Result<()> {
+    T::export_all_to(dir).map_err(|e| Error::Export(format!("{}: {}", T::name(), e)))
+}
+
+fn export_schema<T: JsonSchema>(dir: &Path, name: &str) -> Result<()> {
+    let json = serde_json::to_string_pretty(&schema_for!(T))?;
+    write(&dir.join(format!("{}.schema.json", name)), &(json + "\n"))
+}
+
+fn write(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| Error::io(format!("Failed to create {}", parent.display()), e))?;
+    }
+    fs::write(path, contents).map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))
+}
@@ -0,0 +1,591 @@
+pub mod state;
+
+use serde_json::json;
+use std::fs;
+use uuid::Uuid;
+
+use self::state::*;
+use crate::error::{Error, Result};
+use crate::events::EventSink;
+
+/// Read and parse a file written by [`AppState::save_to_file`]
+pub fn read_session_file(path: &str) -> Result<SessionSaveState> {
+    let contents = fs::read_to_string(path).map_err(|e| Error::io("Failed to read file", e))?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Put a seat secret into a saved session file, leaving the rest as it was saved
+fn write_seat_secret(path: &str, secret: &str) -> Result<()> {
+    let mut saved = read_session_file(path)?;
+    saved.seat_secret = Some(secret.to_string());
+    let json = serde_json::to_string_pretty(&saved)?;
+    fs::write(path, json).map_err(|e| Error::io("Failed to write file", e))
+}
+
+impl AppState {
+    //
+    // Session Config
+    //
+
+    /// Write the session to its configured path. Returns the path.
+    pub async fn save_to_file(&self) -> Result<String> {
+        let session = self.session.lock().await;
+        let config = session.clone().ok_or(Error::NoSession)?;
+        let path = config.path.clone();
+
+        let save_state = SessionSaveState {
+            config,
+            selected_file: self.selected_file.lock().await.clone(),
+            rnbo_patches: self.rnbo_patches.lock().await.clone(),
+            sheet_music: self.sheet_music.lock().await.clone(),
+            phases: self.phases.lock().await.clone(),
+            current_phase_id: self.current_phase_id.lock().await.clone(),
+            seat_secret: self.seat_secret.lock().await.clone(),
+        };
+
+        let json = serde_json::to_string_pretty(&save_state)?;
+        fs::write(&path, json).map_err(|e| Error::io("Failed to write file", e))?;
+        *self.session_file.lock().await = Some(path.clone());
+
+        println!("Session saved to {}", path);
+        Ok(path)
+    }
+
+    pub async fn load_from_file(&self, path: &str) -> Result<()> {
+        let parsed = read_session_file(path)?;
+        self.apply_session(parsed).await;
+        *self.session_file.lock().await = Some(path.to_string());
+
+        println!("Session loaded from {}", path);
+        Ok(())
+    }
+
+    /// Replace the current session with a loaded one
+    pub async fn apply_session(&self, parsed: SessionSaveState) {
+        *self.session.lock().await = Some(parsed.config);
+        *self.selected_file.lock().await = None;
+        *self.rnbo_patches.lock().await = parsed.rnbo_patches;
+        *self.sheet_music.lock().await = parsed.sheet_music;
+        *self.phases.lock().await = parsed.phases;
+        *self.current_phase_id.lock().await = parsed.current_phase_id;
+        *self.seat_secret.lock().await = parsed.seat_secret;
+    }
+
+    pub async fn snapshot(&self) -> AppStateSnapshot {
+        AppStateSnapshot {
+            session: self.session.lock().await.clone(),
+            selected_file: self.selected_file.lock().await.clone(),
+            rnbo_patches: self.rnbo_patches.lock().await.clone(),
+            sheet_music: self.sheet_music.lock().await.clone(),
+            phases: self.phases.lock().await.clone(),
+            current_phase_id: self.current_phase_id.lock().await.clone(),
+        }
+    }
+
+    /// The session's key for signing seat tickets, if tickets were ever issued
+    pub async fn seat_secret(&self) -> Option<String> {
+        self.seat_secret.lock().await.clone()
+    }
+
+    /// The session's key for signing seat tickets, created when the first tickets are issued.
+    /// A new key is written straight into the session file, so printed tickets survive a restart
+    /// without a save. If that fails, the key is still used and goes out with the next save.
+    pub async fn issue_seat_secret(&self) -> String {
+        let mut secret = self.seat_secret.lock().await;
+        if let Some(secret) = secret.as_ref() {
+            return secret.clone();
+        }
+
+        // Two v4 UUIDs: 244 random bits
+        let bytes = [*Uuid::new_v4().as_bytes(), *Uuid::new_v4().as_bytes()].concat();
+        let created = hex::encode(bytes);
+        // An unsaved session gets it with its first save
+        if let Some(path) = self.session_file.lock().await.as_deref() {
+            match write_seat_secret(path, &created) {
+                Ok(()) => println!("Seat secret written to {}", path),
+                Err(e) => eprintln!(
+                    "Failed to write the seat secret to {}, save the session to keep tickets valid: {}",
+                    path, e
+                ),
+            }
+        }
+        *secret = Some(created.clone());
+        created
+    }
+
+    pub async fn set_session_config(&self, config: SessionConfig) {
+        let mut session = self.session.lock().await;
+        // A session with a new save location isn't in any file until it is saved
+        if session.as_ref().is_none_or(|current| current.path != config.path) {
+            *self.session_file.lock().await = None;
+        }
+        *session = Some(config);
+    }
+
+    /// See [`AppState::session_file`](state::AppState::session_file)
+    pub async fn session_file(&self) -> Option<String> {
+        self.session_file.lock().await.clone()
+    }
+
+    pub async fn session_config(&self) -> Option<SessionConfig> {
+        self.session.lock().await.clone()
+    }
+
+    //
+    // Phase Config
+    //
+
+    pub async fn add_phase(&self, data: PhaseInit) -> Result<()> {
+        let mut phases = self.phases.lock().await;
+
+        if phases.contains_key(&data.id) {
+            return Err(Error::PhaseExists(data.id));
+        }
+
+        let config = self.session.lock().await.clone().ok_or(Error::NoSession)?;
+
+        let total_seats = config.rows * config.columns;
+        let assignments: Vec<SeatAssignment> = vec![
+            SeatAssignment {
+                rnbo_id: None,
+                sheet_id: None,
+            };
+            total_seats
+        ];
+
+        phases.insert(
+            data.id.clone(),
+            Phase {
+                name: data.name,
+                bpm: data.bpm,
+                count_in: data.count_in,
+                assignments,
+                index: data.index,
+            },
+        );
+
+        println!("Current phases: {:#?}", phases);
+        Ok(())
+    }
+
+    pub async fn remove_phase(&self, phase_id: &str) -> Result<()> {
+        let mut phases = self.phases.lock().await;
+
+        if phases.remove(phase_id).is_none() {
+            return Err(Error::PhaseNotFound(phase_id.to_string()));
+        }
+
+        let mut current_phase = self.current_phase_id.lock().await;
+        if current_phase.as_deref() == Some(phase_id) {
+            *current_phase = None;
+        }
+
+        println!("Current phases: {:#?}", phases);
+        Ok(())
+    }
+
+    pub async fn edit_phase(&self, phase_id: &str, updates: PhaseUpdate) -> Result<()> {
+        let mut phases = self.phases.lock().await;
+
+        let phase = phases
+            .get_mut(phase_id)
+            .ok_or_else(|| Error::PhaseNotFound(phase_id.to_string()))?;
+
+        if let Some(name) = updates.name {
+            phase.name = if name.trim().is_empty() {
+                "(untitled phase)".to_string()
+            } else {
+                name
+            };
+        }
+        if let Some(bpm) = updates.bpm {
+            phase.bpm = bpm;
+        }
+        if let Some(count_in) = updates.count_in {
+            phase.count_in = count_in;
+        }
+
+        println!("Current phases: {:#?}", phases);
+        Ok(())
+    }
+
+    pub async fn set_current_phase(&self, phase_id: &str, events: &dyn EventSink) -> Result<()> {
+        let phases = self.phases.lock().await;
+        if !phases.contains_key(phase_id) {
+            return Err(Error::PhaseNotFound(phase_id.to_string()));
+        }
+        drop(phases); // unlock before locking again
+
+        *self.current_phase_id.lock().await = Some(phase_id.to_string());
+
+        println!("Current phase set to: {}", phase_id);
+        events.emit("phase-changed", json!(phase_id));
+        Ok(())
+    }
+
+    pub async fn current_phase(&self) -> Option<String> {
+        self.current_phase_id.lock().await.clone()
+    }
+
+    pub async fn clear_current_phase(&self, events: &dyn EventSink) {
+        *self.current_phase_id.lock().await = None;
+
+        println!("Current phase cleared.");
+        events.emit("phase-changed", json!(""));
+    }
+
+    //
+    // Palette Item
+    //
+
+    pub async fn add_rnbo_file(&self, item: RNBOPaletteItem) {
+        let mut rnbo = self.rnbo_patches.lock().await;
+        rnbo.push(item);
+
+        println!("Current RNBO files:");
+        for f in rnbo.iter() {
+            println!("- {} ({})", f.label, f.path);
+        }
+    }
+
+    pub async fn remove_rnbo_file(&self, id: &str, events: &dyn EventSink) {
+        self.rnbo_patches.lock().await.retain(|item| item.id != id);
+
+        // Also clear any references in phase assignments
+        let mut phases = self.phases.lock().await;
+        for phase in phases.values_mut() {
+            for seat in &mut phase.assignments {
+                if seat.rnbo_id.as_deref() == Some(id) {
+                    seat.rnbo_id = None;
+                }
+            }
+        }
+
+        events.emit("palette-item-removed", json!(id));
+    }
+
+    pub async fn add_sheet_file(&self, item: SheetPaletteItem) {
+        let mut sheet = self.sheet_music.lock().await;
+        sheet.push(item);
+
+        println!("Current sheet files:");
+        for f in sheet.iter() {
+            println!("- {} ({})", f.label, f.path);
+        }
+    }
+
+    pub async fn remove_sheet_file(&self, id: &str, events: &dyn EventSink) {
+        self.sheet_music.lock().await.retain(|item| item.id != id);
+
+        // Also clear any references in phase assignments
+        let mut phases = self.phases.lock().await;
+        for phase in phases.values_mut() {
+            for seat in &mut phase.assignments {
+                if seat.sheet_id.as_deref() == Some(id) {
+                    seat.sheet_id = None;
+                }
+            }
+        }
+
+        events.emit("palette-item-removed", json!(id));
+    }
+
+    pub async fn selected_file(&self) -> Option<SelectedFile> {
+        self.selected_file.lock().await.clone()
+    }
+
+    pub async fn select_palette_file(&self, id: &str, file_type: &str) -> Result<()> {
+        let selected = match file_type {
+            "rnbo" => {
+                let file = self.rnbo_item(id).await?;
+                SelectedFile {
+                    id: file.id,
+                    label: file.label,
+                    path: file.path,
+                    color: file.color,
+                    file_type: "rnbo".into(),
+                }
+            }
+            "sheet" => {
+                let file = self.sheet_item(id).await?;
+                SelectedFile {
+                    id: file.id,
+                    label: file.label,
+                    path: file.path,
+                    color: file.color,
+                    file_type: "sheet".into(),
+                }
+            }
+            _ => return Err(Error::InvalidFileType(file_type.to_string())),
+        };
+
+        *self.selected_file.lock().await = Some(selected);
+
+        println!("Selected file updated.");
+        Ok(())
+    }
+
+    pub async fn clear_selected_file(&self) {
+        *self.selected_file.lock().await = None;
+
+        println!("Selected file cleared.");
+    }
+
+    pub async fn assign_file_to_seat(
+        &self,
+        phase_id: &str,
+        seat_index: usize,
+        file_id: &str,
+        file_type: &str,
+    ) -> Result<()> {
+        let mut phases = self.phases.lock().await;
+        let phase = phases
+            .get_mut(phase_id)
+            .ok_or_else(|| Error::PhaseNotFound(phase_id.to_string()))?;
+
+        let assignment = phase
+            .assignments
+            .get_mut(seat_index)
+            .ok_or(Error::InvalidSeatIndex(seat_index))?;
+        match file_type {
+            "rnbo" => assignment.rnbo_id = Some(file_id.to_string()),
+            "sheet" => assignment.sheet_id = Some(file_id.to_string()),
+            _ => return Err(Error::InvalidFileType(file_type.to_string())),
+        }
+
+        println!("Seat {seat_index} updated with {file_type} {file_id}");
+        Ok(())
+    }
+
+    pub async fn unassign_file_from_seat(
+        &self,
+        phase_id: &str,
+        seat_index: usize,
+        file_type: &str,
+    ) -> Result<()> {
+        let mut phases = self.phases.lock().await;
+        let phase = phases
+            .get_mut(phase_id)
+            .ok_or_else(|| Error::PhaseNotFound(phase_id.to_string()))?;
+
+        let assignment = phase
+            .assignments
+            .get_mut(seat_index)
+            .ok_or(Error::InvalidSeatIndex(seat_index))?;
+        match file_type {
+            "rnbo" => assignment.rnbo_id = None,
+            "sheet" => assignment.sheet_id = None,
+            _ => return Err(Error::InvalidFileType(file_type.to_string())),
+        }
+
+        println!("Unassigned {file_type} from seat {seat_index}");
+        Ok(())
+    }
+
+    pub async fn assignments_for_phase(&self, phase_id: &str) -> Result<Vec<SeatAssignment>> {
+        let phases = self.phases.lock().await;
+        let phase = phases
+            .get(phase_id)
+            .ok_or_else(|| Error::PhaseNotFound(phase_id.to_string()))?;
+
+        Ok(phase.assignments.clone())
+    }
+
+    pub async fn rnbo_item(&self, id: &str) -> Result<RNBOPaletteItem> {
+        self.rnbo_patches
+            .lock()
+            .await
+            .iter()
+            .find(|item| item.id == id)
+            .cloned()
+            .ok_or_else(|| Error::PaletteItemNotFound {
+                kind: "RNBO",
+                id: id.to_string(),
+            })
+    }
+
+    pub async fn sheet_item(&self, id: &str) -> Result<SheetPaletteItem> {
+        self.sheet_music
+            .lock()
+            .await
+            .iter()
+            .find(|item| item.id == id)
+            .cloned()
+            .ok_or_else(|| Error::PaletteItemNotFound {
+                kind: "sheet",
+                id: id.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorded(Mutex<Vec<(String, Value)>>);
+
+    impl EventSink for Recorded {
+        fn emit(&self, event: &str, payload: Value) {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    /// A 2x2 session with one phase, "intro"
+    async fn designed() -> AppState {
+        let state = AppState::default();
+        state
+            .set_session_config(SessionConfig { name: "test".into(), path: "test.json".into(), rows: 2, columns: 2 })
+            .await;
+        state
+            .add_phase(PhaseInit { id: "intro".into(), name: "Intro".into(), bpm: 90, count_in: 4, index: 0 })
+            .await
+            .unwrap();
+        state
+    }
+
+    fn rnbo(id: &str) -> RNBOPaletteItem {
+        RNBOPaletteItem { id: id.into(), label: id.into(), color: "#fff".into(), path: format!("{id}.json") }
+    }
+
+    fn sheet(id: &str) -> SheetPaletteItem {
+        SheetPaletteItem { id: id.into(), label: id.into(), color: "#000".into(), path: format!("{id}.json") }
+    }
+
+    #[tokio::test]
+    async fn phases_need_a_session_and_a_fresh_id() {
+        let init = |id: &str| PhaseInit { id: id.into(), name: id.into(), bpm: 120, count_in: 0, index: 1 };
+        assert!(matches!(AppState::default().add_phase(init("verse")).await, Err(Error::NoSession)));
+
+        let state = designed().await;
+        assert!(matches!(state.add_phase(init("intro")).await, Err(Error::PhaseExists(id)) if id == "intro"));
+        state.add_phase(init("verse")).await.unwrap();
+
+        let assignments = state.assignments_for_phase("verse").await.unwrap();
+        assert_eq!(assignments.len(), 4);
+        assert!(assignments.iter().all(|seat| seat.rnbo_id.is_none() && seat.sheet_id.is_none()));
+    }
+
+    #[tokio::test]
+    async fn removing_the_current_phase_clears_it() {
+        let state = designed().await;
+        let events = Recorded::default();
+        state.set_current_phase("intro", &events).await.unwrap();
+        assert_eq!(state.current_phase().await.as_deref(), Some("intro"));
+        assert!(matches!(state.set_current_phase("outro", &events).await, Err(Error::PhaseNotFound(_))));
+
+        state.remove_phase("intro").await.unwrap();
+        assert_eq!(state.current_phase().await, None);
+        assert!(matches!(state.remove_phase("intro").await, Err(Error::PhaseNotFound(_))));
+        assert_eq!(*events.0.lock().unwrap(), vec![("phase-changed".to_string(), json!("intro"))]);
+    }
+
+    #[tokio::test]
+    async fn edits_change_only_what_is_given() {
+        let state = designed().await;
+        let update = PhaseUpdate { name: Some("  ".into()), bpm: Some(140), count_in: None };
+        state.edit_phase("intro", update).await.unwrap();
+
+        let phases = state.phases.lock().await;
+        let phase = &phases["intro"];
+        assert_eq!(phase.name, "(untitled phase)");
+        assert_eq!(phase.bpm, 140);
+        assert_eq!(phase.count_in, 4);
+    }
+
+    #[tokio::test]
+    async fn assigns_and_unassigns_files_per_seat() {
+        let state = designed().await;
+        state.assign_file_to_seat("intro", 3, "synth", "rnbo").await.unwrap();
+        state.assign_file_to_seat("intro", 3, "score", "sheet").await.unwrap();
+        assert!(matches!(state.assign_file_to_seat("intro", 4, "synth", "rnbo").await, Err(Error::InvalidSeatIndex(4))));
+        assert!(matches!(state.assign_file_to_seat("intro", 0, "synth", "wav").await, Err(Error::InvalidFileType(_))));
+        assert!(matches!(state.assign_file_to_seat("outro", 0, "synth", "rnbo").await, Err(Error::PhaseNotFound(_))));
+
+        state.unassign_file_from_seat("intro", 3, "rnbo").await.unwrap();
+        let seat = &state.assignments_for_phase("intro").await.unwrap()[3];
+        assert_eq!(seat.rnbo_id, None);
+        assert_eq!(seat.sheet_id.as_deref(), Some("score"));
+    }
+
+    #[tokio::test]
+    async fn removing_a_palette_item_clears_its_assignments() {
+        let state = designed().await;
+        let events = Recorded::default();
+        state.add_rnbo_file(rnbo("synth")).await;
+        state.add_sheet_file(sheet("score")).await;
+        state.assign_file_to_seat("intro", 0, "synth", "rnbo").await.unwrap();
+        state.assign_file_to_seat("intro", 0, "score", "sheet").await.unwrap();
+
+        state.remove_rnbo_file("synth", &events).await;
+        assert!(matches!(state.rnbo_item("synth").await, Err(Error::PaletteItemNotFound { kind: "RNBO", .. })));
+        let seat = &state.assignments_for_phase("intro").await.unwrap()[0];
+        assert_eq!(seat.rnbo_id, None);
+        assert_eq!(seat.sheet_id.as_deref(), Some("score"));
+
+        state.remove_sheet_file("score", &events).await;
+        assert_eq!(state.assignments_for_phase("intro").await.unwrap()[0].sheet_id, None);
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            vec![
+                ("palette-item-removed".to_string(), json!("synth")),
+                ("palette-item-removed".to_string(), json!("score")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn selects_palette_items_by_kind() {
+        let state = designed().await;
+        state.add_rnbo_file(rnbo("synth")).await;
+        state.add_sheet_file(sheet("score")).await;
+
+        state.select_palette_file("score", "sheet").await.unwrap();
+        let selected = state.selected_file().await.unwrap();
+        assert_eq!((selected.id.as_str(), selected.file_type.as_str()), ("score", "sheet"));
+
+        // A sheet id isn't an RNBO patch, and a failed selection keeps the last one
+        assert!(matches!(state.select_palette_file("score", "rnbo").await, Err(Error::PaletteItemNotFound { .. })));
+        assert!(matches!(state.select_palette_file("synth", "midi").await, Err(Error::InvalidFileType(_))));
+        assert_eq!(state.selected_file().await.unwrap().id, "score");
+
+        state.clear_selected_file().await;
+        assert!(state.selected_file().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn an_issued_seat_secret_is_written_to_the_loaded_file() {
+        let path = std::env::temp_dir().join(format!("rtpc-seat-secret-{}.json", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let session = json!({
+            "config": { "name": "test", "path": "elsewhere/test.json", "rows": 1, "columns": 2 },
+            "selected_file": null,
+            "rnbo_patches": [],
+            "sheet_music": [],
+            "phases": {},
+            "current_phase_id": null
+        });
+        fs::write(&path, session.to_string()).unwrap();
+
+        let state = AppState::default();
+        state.load_from_file(&path).await.unwrap();
+        assert_eq!(state.seat_secret().await, None);
+        let secret = state.issue_seat_secret().await;
+        assert_eq!(state.issue_seat_secret().await, secret);
+
+        let saved = read_session_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved.seat_secret, Some(secret));
+        assert_eq!(saved.config.name, "test");
+    }
+
+    #[tokio::test]
+    async fn a_seat_secret_that_cant_be_written_is_still_issued() {
+        let state = AppState::default();
+        *state.session_file.lock().await = Some("/nonexistent/rtpc/session.json".into());
+        let secret = state.issue_seat_secret().await;
+        assert_eq!(state.seat_secret().await, Some(secret));
+    }
+}
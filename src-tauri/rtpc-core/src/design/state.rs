@@ -0,0 +1,197 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use ts_rs::TS;
+
+
+
+//
+// Session Info
+//
+
+#[derive(Clone, Serialize, Deserialize, TS, JsonSchema)]
+pub struct SessionConfig {
+    pub name: String,
+    pub path: String,
+    pub rows: usize,
+    pub columns: usize,
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema)]
+pub struct SessionSaveState {
+    pub config: SessionConfig,
+    pub selected_file: Option<SelectedFile>,
+    pub rnbo_patches: Vec<RNBOPaletteItem>,
+    pub sheet_music: Vec<SheetPaletteItem>,
+    pub phases: HashMap<String, Phase>,
+    pub current_phase_id: Option<String>,
+    /// Key for signing seat tickets, kept so printed QR codes stay valid across restarts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub seat_secret: Option<String>,
+}
+
+/// Everything in the current session, as shown by the admin UI
+#[derive(Serialize, TS, JsonSchema)]
+pub struct AppStateSnapshot {
+    pub session: Option<SessionConfig>,
+    pub selected_file: Option<SelectedFile>,
+    pub rnbo_patches: Vec<RNBOPaletteItem>,
+    pub sheet_music: Vec<SheetPaletteItem>,
+    pub phases: HashMap<String, Phase>,
+    pub current_phase_id: Option<String>,
+}
+
+
+//
+// Palette Item
+//
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, TS, JsonSchema)]
+pub struct RNBOPaletteItem {
+    pub id: String,
+    pub label: String,
+    pub color: String,
+    pub path: String, // absolute or relative path to the file
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, TS, JsonSchema)]
+pub struct SheetPaletteItem {
+    pub id: String,
+    pub label: String,
+    pub color: String,
+    pub path: String, // absolute or relative path to the file
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, TS, JsonSchema)]
+pub struct SelectedFile {
+    pub id: String,
+    pub label: String,
+    pub path: String,
+    pub color: String,
+    pub file_type: String, // "rnbo" or "sheet"
+}
+
+
+//
+// Seat Assignment
+//
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS, JsonSchema)]
+pub struct SeatAssignment {
+    pub rnbo_id: Option<String>,
+    pub sheet_id: Option<String>,
+}
+
+//
+// Phase
+//
+#[derive(Debug, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseInit {
+    pub id: String,
+    pub name: String,
+    pub bpm: u32,
+    pub count_in: u32,
+    pub index: usize,
+}
+
+#[derive(Debug, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseUpdate {
+    pub name: Option<String>,
+    pub bpm: Option<u32>,
+    pub count_in: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS, JsonSchema)]
+pub struct Phase {
+    pub name: String,
+    pub assignments: Vec<SeatAssignment>,
+    pub bpm: u32,
+    pub count_in: u32,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, TS, JsonSchema)]
+pub struct PhaseStartPayload {
+  pub bpm: u32,
+  pub count_in: u32,
+  #[ts(type = "number")]
+  pub start_time: i64,
+  pub assignments: HashMap<String, AssignmentPayload>,
+}
+
+/// A phase that is already playing, for a client that joined or reconnected after it started
+#[derive(Debug, serde::Serialize, TS, JsonSchema)]
+pub struct PhaseCatchUpPayload {
+  #[serde(flatten)]
+  pub phase: PhaseStartPayload,
+  /// Beats since the downbeat at `server_time`; negative during the count-in
+  pub current_beat: f64,
+  #[ts(type = "number")]
+  pub server_time: i64,
+  /// Where the client comes in: the first bar line it can still reach in time
+  #[ts(type = "number")]
+  pub entry_beat: i64,
+  #[ts(type = "number")]
+  pub entry_time: i64,
+}
+
+#[derive(Debug, serde::Serialize, TS, JsonSchema)]
+pub struct PhaseStopPayload {
+  #[ts(type = "number")]
+  pub stop_time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, JsonSchema)]
+pub struct AssignmentPayload {
+  pub rnbo_id: String,
+  pub sheet_id: String,
+}
+
+//
+// Sheet Music Format
+//
+
+/// A sheet music file as read by the client. The backend only stores and serves these.
+#[derive(Debug, Clone, Serialize, Deserialize, TS, JsonSchema)]
+pub struct SheetFile {
+    pub bpm: f64,
+    pub end_beat: f64,
+    pub tracks: Vec<SheetTrack>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS, JsonSchema)]
+pub struct SheetTrack {
+    pub instrument: String,
+    pub channel: u8,
+    pub notes: Vec<SheetNote>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS, JsonSchema)]
+pub struct SheetNote {
+    pub pitch: u8,
+    pub velocity: u8,
+    pub start: f64, // in beats
+    pub duration: String, // e.g. "4n", "8t"
+}
+
+//
+// Global App State
+//
+
+#[derive(Default)]
+pub struct AppState {
+    pub session: Mutex<Option<SessionConfig>>,
+    pub selected_file: Mutex<Option<SelectedFile>>,
+    pub rnbo_patches: Mutex<Vec<RNBOPaletteItem>>,
+    pub sheet_music: Mutex<Vec<SheetPaletteItem>>,
+    pub phases: Mutex<HashMap<String, Phase>>,
+    pub current_phase_id: Mutex<Option<String>>,
+    pub seat_secret: Mutex<Option<String>>,
+    /// The file the session was last loaded from or saved to, which may differ from
+    /// `config.path` for a session file copied from another machine
+    pub session_file: Mutex<Option<String>>,
+}
\ No newline at end of file
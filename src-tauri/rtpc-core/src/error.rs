@@ -0,0 +1,72 @@
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("No session loaded")]
+    NoSession,
+    #[error("Phase `{0}` already exists")]
+    PhaseExists(String),
+    #[error("Phase `{0}` not found")]
+    PhaseNotFound(String),
+    #[error("Invalid seat index {0}")]
+    InvalidSeatIndex(usize),
+    #[error("No seat `{seat}` in this session ({rows} × {columns} seats)")]
+    InvalidSeat { seat: String, rows: usize, columns: usize },
+    #[error("Seat `{0}` is already taken")]
+    SeatTaken(String),
+    #[error("Nobody is in seat `{0}`")]
+    SeatEmpty(String),
+    #[error("No client with id {0}")]
+    ClientNotFound(String),
+    #[error("Client is not keeping up with its messages")]
+    ClientLagging,
+    #[error("Connection closed")]
+    ConnectionClosed,
+    #[error("Invalid file type `{0}`")]
+    InvalidFileType(String),
+    #[error("No {kind} item found with id {id}")]
+    PaletteItemNotFound { kind: &'static str, id: String },
+    #[error("Server is not running")]
+    ServerNotRunning,
+    #[error("Server is already running")]
+    AlreadyRunning,
+    #[error("Failed to listen on port {port}: {reason}")]
+    Bind { port: u16, reason: String },
+    #[error("Invalid server options: {0}")]
+    InvalidOptions(String),
+    #[error("Failed to list network interfaces: {0}")]
+    Network(String),
+    #[error("mDNS advertisement failed: {0}")]
+    Mdns(String),
+    #[error("Failed to render QR code: {0}")]
+    Qr(String),
+    #[error("TLS setup failed: {0}")]
+    Tls(String),
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Failed to export bindings for {0}")]
+    Export(String),
+}
+
+impl Error {
+    pub fn bind(port: u16, source: impl std::fmt::Display) -> Self {
+        Error::Bind {
+            port,
+            reason: source.to_string(),
+        }
+    }
+
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        Error::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Session design model and performance broker for RTPC.
+//!
+//! Nothing in here depends on Tauri. Front-ends edit the session through the operations on
+//! [`AppState`](state::AppState), run a [`ServerController`](server_controller::ServerController)
+//! and receive broker events through an [`EventSink`](events::EventSink).
+
+pub mod bindings;
+pub mod design;
+pub mod error;
+pub mod server;
+
+pub use design::*;
+pub use error::{Error, Result};
+pub use server::*;
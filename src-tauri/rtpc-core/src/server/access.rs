@@ -0,0 +1,174 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::protocol::{ErrorCode, ServerMessage};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Seat tokens are the first 16 bytes of the HMAC, short enough for a QR code
+const SEAT_TOKEN_LEN: usize = 16;
+
+/// Who may take a seat: an optional session join code, and per-seat tokens signed with the
+/// session's seat secret. A valid seat token admits its holder without the join code.
+#[derive(Debug, Clone, Default)]
+pub struct JoinAccess {
+    pub code: Option<String>,
+    /// Only holders of a seat's token may take it
+    pub require_seat_token: bool,
+    secret: Option<Vec<u8>>,
+}
+
+impl JoinAccess {
+    /// `secret` is the session's seat secret; without one, no seat tokens are issued or accepted
+    pub fn new(code: Option<String>, require_seat_token: bool, secret: Option<&str>) -> Self {
+        JoinAccess {
+            code: code.map(|code| code.trim().to_string()).filter(|code| !code.is_empty()),
+            require_seat_token,
+            secret: secret.map(|secret| secret.as_bytes().to_vec()),
+        }
+    }
+
+    /// Whether there is a seat secret to sign and check tickets with
+    pub fn has_secret(&self) -> bool {
+        self.secret.is_some()
+    }
+
+    pub fn set_secret(&mut self, secret: &str) {
+        self.secret = Some(secret.as_bytes().to_vec());
+    }
+
+    /// The ticket for `seat`, to embed in its QR code
+    pub fn seat_token(&self, seat: &str) -> Option<String> {
+        let mac = self.mac(seat)?;
+        Some(hex::encode(&mac.finalize().into_bytes()[..SEAT_TOKEN_LEN]))
+    }
+
+    /// Check a join request. Returns the rejection to send back, if any.
+    pub fn check(&self, seat: &str, code: Option<&str>, token: Option<&str>) -> Result<(), ServerMessage> {
+        if let Some(token) = token.filter(|token| !token.is_empty()) {
+            return if self.verify_seat_token(seat, token) {
+                Ok(())
+            } else {
+                Err(ServerMessage::error(
+                    ErrorCode::SeatTokenInvalid,
+                    "This ticket is not valid for this seat",
+                ))
+            };
+        }
+
+        if self.require_seat_token {
+            return Err(ServerMessage::error(
+                ErrorCode::SeatTokenRequired,
+                "Scan the QR code on your seat to join",
+            ));
+        }
+
+        match (&self.code, code.map(str::trim).filter(|code| !code.is_empty())) {
+            (None, _) => Ok(()),
+            (Some(_), None) => Err(ServerMessage::error(
+                ErrorCode::JoinCodeRequired,
+                "Enter the join code to take a seat",
+            )),
+            (Some(expected), Some(given)) if constant_time_eq(expected.as_bytes(), given.as_bytes()) => Ok(()),
+            (Some(_), Some(_)) => Err(ServerMessage::error(ErrorCode::JoinCodeInvalid, "Wrong join code")),
+        }
+    }
+
+    fn verify_seat_token(&self, seat: &str, token: &str) -> bool {
+        let Ok(tag) = hex::decode(token.trim()) else {
+            return false;
+        };
+        if tag.len() != SEAT_TOKEN_LEN {
+            return false;
+        }
+        self.mac(seat)
+            .is_some_and(|mac| mac.verify_truncated_left(&tag).is_ok())
+    }
+
+    fn mac(&self, seat: &str) -> Option<HmacSha256> {
+        let secret = self.secret.as_ref()?;
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any length");
+        mac.update(b"seat:");
+        mac.update(seat.as_bytes());
+        Some(mac)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    /// The error code a check was rejected with, if it was
+    fn rejection(result: Result<(), ServerMessage>) -> Option<ErrorCode> {
+        match result {
+            Ok(()) => None,
+            Err(ServerMessage::Error { code, .. }) => Some(code),
+            Err(other) => panic!("unexpected rejection {:?}", other),
+        }
+    }
+
+    #[test]
+    fn checks_the_join_code() {
+        let open = JoinAccess::new(None, false, None);
+        assert!(open.check("3", None, None).is_ok());
+
+        let access = JoinAccess::new(Some(" 4711 ".into()), false, None);
+        assert!(access.check("3", Some("4711"), None).is_ok());
+        assert!(access.check("3", Some(" 4711\n"), None).is_ok());
+        assert!(matches!(rejection(access.check("3", None, None)), Some(ErrorCode::JoinCodeRequired)));
+        assert!(matches!(rejection(access.check("3", Some(""), None)), Some(ErrorCode::JoinCodeRequired)));
+        assert!(matches!(rejection(access.check("3", Some("4712"), None)), Some(ErrorCode::JoinCodeInvalid)));
+        assert!(matches!(rejection(access.check("3", Some("47110"), None)), Some(ErrorCode::JoinCodeInvalid)));
+    }
+
+    #[test]
+    fn a_ticket_admits_to_its_own_seat_only() {
+        let access = JoinAccess::new(Some("4711".into()), true, Some(SECRET));
+        let ticket = access.seat_token("3").expect("a ticket");
+        assert_eq!(ticket.len(), SEAT_TOKEN_LEN * 2);
+
+        // Without the join code, too
+        assert!(access.check("3", None, Some(&ticket)).is_ok());
+        assert!(matches!(rejection(access.check("4", None, Some(&ticket))), Some(ErrorCode::SeatTokenInvalid)));
+        // Required tickets can't be replaced by the code
+        assert!(matches!(rejection(access.check("3", Some("4711"), None)), Some(ErrorCode::SeatTokenRequired)));
+    }
+
+    #[test]
+    fn rejects_tickets_from_another_secret() {
+        let access = JoinAccess::new(None, true, Some(SECRET));
+        let other = JoinAccess::new(None, true, Some("another secret"));
+        let ticket = other.seat_token("3").expect("a ticket");
+        assert!(matches!(rejection(access.check("3", None, Some(&ticket))), Some(ErrorCode::SeatTokenInvalid)));
+
+        // No secret, no tickets
+        let unsigned = JoinAccess::new(None, false, None);
+        assert_eq!(unsigned.seat_token("3"), None);
+        let ticket = access.seat_token("3").expect("a ticket");
+        assert!(matches!(rejection(unsigned.check("3", None, Some(&ticket))), Some(ErrorCode::SeatTokenInvalid)));
+    }
+
+    #[test]
+    fn rejects_malformed_tickets() {
+        let access = JoinAccess::new(None, true, Some(SECRET));
+        let ticket = access.seat_token("3").expect("a ticket");
+        let truncated = &ticket[..ticket.len() - 2];
+        let extended = format!("{}00", ticket);
+        let flipped = format!("{}{}", if ticket.starts_with('0') { '1' } else { '0' }, &ticket[1..]);
+        for bad in [truncated, &extended, &flipped, "not hex at all", "zz", &ticket[..1]] {
+            assert!(
+                matches!(rejection(access.check("3", None, Some(bad))), Some(ErrorCode::SeatTokenInvalid)),
+                "ticket {:?}",
+                bad
+            );
+        }
+        // An empty ticket counts as none
+        assert!(matches!(rejection(access.check("3", None, Some(""))), Some(ErrorCode::SeatTokenRequired)));
+    }
+}
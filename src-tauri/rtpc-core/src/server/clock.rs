@@ -0,0 +1,65 @@
+use chrono::Utc;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Source of server time (ms since the UNIX epoch) for replies, phase starts and cues
+pub trait Clock: Debug + Send + Sync {
+    fn now_ms(&self) -> i64;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Wall clock read once when the server starts, then advanced with a monotonic `Instant`.
+/// NTP steps or manual clock changes during a show don't move it.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    anchor_ms: i64,
+    anchor: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        MonotonicClock {
+            anchor_ms: Utc::now().timestamp_millis(),
+            anchor: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_ms(&self) -> i64 {
+        self.anchor_ms + self.anchor.elapsed().as_millis() as i64
+    }
+}
+
+/// A clock that only moves when told to, so tests can check scheduling to the millisecond
+#[derive(Debug, Default)]
+pub struct FixedClock(AtomicI64);
+
+impl FixedClock {
+    pub fn new(now_ms: i64) -> Self {
+        FixedClock(AtomicI64::new(now_ms))
+    }
+
+    pub fn set(&self, now_ms: i64) {
+        self.0.store(now_ms, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, ms: i64) {
+        self.0.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now_ms(&self) -> i64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,19 @@
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Receiver for broker and session events (client-connected, seat-expired, phase-changed, ...)
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: &str, payload: Value);
+}
+
+pub type SharedEvents = Arc<dyn EventSink>;
+
+/// Prints events to stdout, one JSON line each. Used by the headless server.
+#[derive(Debug, Default)]
+pub struct StdoutEvents;
+
+impl EventSink for StdoutEvents {
+    fn emit(&self, event: &str, payload: Value) {
+        println!("[event] {} {}", event, payload);
+    }
+}
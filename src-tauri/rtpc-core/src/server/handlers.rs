@@ -0,0 +1,575 @@
+use crate::performance_types::{ClientInfo, ClientStatus, PerformanceState};
+use crate::clock::SharedClock;
+use crate::protocol::{
+    ClientMessage, ErrorCode, FileType, ManifestEntry, SeatValue, ServerMessage, PROTOCOL_VERSION,
+};
+use crate::outbox::ClientSender;
+use crate::seats::{self, Seat};
+use crate::time_sync::TimeSample;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::ws::Message;
+use uuid::Uuid;
+use crate::state::{AppState, PhaseCatchUpPayload};
+use std::collections::{HashMap, HashSet};
+use sha2::{Sha256, Digest};
+use tokio::fs;
+use crate::events::SharedEvents;
+
+
+/// Main entry point for handling incoming WebSocket messages
+pub async fn handle_message(
+    msg: &str,
+    perf_state: Arc<tokio::sync::Mutex<PerformanceState>>,
+    app_state: Arc<AppState>,
+    sender: ClientSender,
+    events: SharedEvents,
+    clock: SharedClock,
+) -> Option<Message> {
+    // Parse incoming string into a typed message
+    let parsed: ClientMessage = match serde_json::from_str(msg) {
+        Ok(val) => val,
+        Err(e) if e.is_syntax() || e.is_eof() => {
+            return Some(ServerMessage::error(ErrorCode::InvalidJson, "Invalid JSON").into());
+        }
+        Err(e) => {
+            return Some(ServerMessage::error(ErrorCode::InvalidMessage, e.to_string()).into());
+        }
+    };
+
+    perf_state.lock().await.touch(&sender, clock.now_ms() as u64);
+
+    let reply = match parsed {
+        ClientMessage::Ping => Some(ServerMessage::Pong),
+        ClientMessage::Join { seat, protocol, code, token } => {
+            let request = JoinRequest { seat, protocol, code, token };
+            handle_join(request, &app_state, perf_state.clone(), sender.clone(), events, &clock).await
+        }
+        ClientMessage::Rejoin { id, protocol } => {
+            handle_rejoin(&id, protocol, &app_state, perf_state.clone(), sender.clone(), events, &clock).await
+        }
+        ClientMessage::Refresh { id } => handle_refresh(&id, perf_state.clone(), &clock).await,
+        ClientMessage::TimeRequest { client_time } => handle_time_request(client_time, &clock),
+        ClientMessage::TimeSample { client_time, server_time, receive_time } => {
+            let sample = TimeSample {
+                client_send: client_time,
+                server_time,
+                client_receive: receive_time,
+            };
+            handle_time_sample(sample, perf_state.clone(), sender.clone(), events, &clock).await
+        }
+        ClientMessage::Ready { id } => handle_ready(app_state.clone(), &id, perf_state.clone()).await,
+        ClientMessage::FileRequest { name, file_type } => {
+            handle_file_request(name, file_type, perf_state.clone(), sender.clone(), app_state.clone(), events).await
+        }
+        ClientMessage::Loaded { id } => handle_loaded(&id, perf_state.clone(), events, &clock).await,
+        ClientMessage::Unknown => Some(ServerMessage::error(ErrorCode::UnknownMessage, "Unknown message type")),
+    };
+
+    reply.map(Message::from)
+}
+
+/// Handle a time_request message by replying with a tq_result
+fn handle_time_request(client_time: i64, clock: &SharedClock) -> Option<ServerMessage> {
+    let resp = ServerMessage::TimeResult {
+        client_time,
+        server_time: clock.now_ms(),
+    };
+    println!("[time_request] {:?}", resp);
+    Some(resp)
+}
+
+/// Handle a client reporting a completed time_request round trip.
+/// The sample feeds that seat's offset, RTT and jitter estimates.
+async fn handle_time_sample(
+    sample: TimeSample,
+    state: Arc<Mutex<PerformanceState>>,
+    sender: ClientSender,
+    events: SharedEvents,
+    clock: &SharedClock,
+) -> Option<ServerMessage> {
+    let mut locked = state.lock().await;
+    let seat = locked.seat_for_sender(&sender)?;
+    let info = locked.seat_map.get_mut(&seat)?;
+    if !info.sync.add_sample(sample, clock.now_ms()) {
+        return None;
+    }
+    let synced = info.status == ClientStatus::Joined;
+    drop(locked);
+
+    if synced {
+        update_status(&state, &sender, &events, ClientStatus::Synced).await;
+    }
+    None
+}
+
+/// A client asking for a seat, with whatever credentials it has
+#[derive(Debug)]
+pub struct JoinRequest {
+    pub seat: SeatValue,
+    pub protocol: Option<u32>,
+    pub code: Option<String>,
+    pub token: Option<String>,
+}
+
+fn joined(id: String, seat: Seat, expires_at: u64) -> ServerMessage {
+    ServerMessage::Joined {
+        id,
+        seat: seat.key(),
+        row: seat.row_label(),
+        column: seat.number(),
+        expires_at,
+        protocol: PROTOCOL_VERSION,
+    }
+}
+
+/// The reply to a join or rejoin. While a phase is playing, the client also gets a catch-up right
+/// after it, so it knows what it is walking into before its files are loaded.
+fn joined_during_phase(
+    sender: &ClientSender,
+    joined: ServerMessage,
+    catch_up: Option<PhaseCatchUpPayload>,
+) -> Option<ServerMessage> {
+    let Some(catch_up) = catch_up else {
+        return Some(joined);
+    };
+    let _ = sender.send(joined);
+    let _ = sender.send(ServerMessage::PhaseCatchUp(catch_up));
+    None
+}
+
+/// Seats with something assigned in the phase that is playing, or else the one selected in the editor
+async fn assigned_seats(app_state: &AppState, state: &Mutex<PerformanceState>) -> HashSet<usize> {
+    let playing = state.lock().await.active_phase.as_ref().map(|active| active.phase_id.clone());
+    let Some(phase_id) = playing.or(app_state.current_phase_id.lock().await.clone()) else {
+        return HashSet::new();
+    };
+
+    let phases = app_state.phases.lock().await;
+    let Some(phase) = phases.get(&phase_id) else {
+        return HashSet::new();
+    };
+    phase
+        .assignments
+        .iter()
+        .enumerate()
+        .filter(|(_, assign)| assign.rnbo_id.is_some() || assign.sheet_id.is_some())
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Handle a new client joining the session.
+/// The seat may be given as its index, as row and seat number (e.g. `C4`), or as `any` to have
+/// the server pick one.
+pub async fn handle_join(
+    request: JoinRequest,
+    app_state: &AppState,
+    state: Arc<tokio::sync::Mutex<PerformanceState>>,
+    sender: ClientSender,
+    events: SharedEvents,
+    clock: &SharedClock,
+) -> Option<ServerMessage> {
+    if let Some(err) = ServerMessage::check_protocol(request.protocol) {
+        return Some(err);
+    }
+
+    let Some(config) = app_state.session_config().await else {
+        return Some(ServerMessage::error(ErrorCode::InvalidSeat, "No session is loaded"));
+    };
+    let wanted = request.seat.into_string();
+    let any = wanted.eq_ignore_ascii_case(seats::ANY_SEAT);
+    let preferred = if any { assigned_seats(app_state, &state).await } else { HashSet::new() };
+
+    // Lock to access TTL and insert data
+    let mut locked = state.lock().await;
+
+    let place = if any {
+        let perf = &mut *locked;
+        match perf.allocator.pick(&config, |seat| perf.seat_map.contains_key(&seat.key()), &preferred) {
+            Some(place) => place,
+            None => return Some(ServerMessage::error(ErrorCode::NoFreeSeat, "Every seat is taken")),
+        }
+    } else {
+        match seats::resolve(&config, &wanted) {
+            Ok(place) => place,
+            Err(e) => return Some(ServerMessage::error(ErrorCode::InvalidSeat, e.to_string())),
+        }
+    };
+    let seat = place.key();
+
+    if let Err(rejection) = locked.access.check(&seat, request.code.as_deref(), request.token.as_deref()) {
+        return Some(rejection);
+    }
+
+    if locked.seat_map.contains_key(&seat) {
+        return Some(ServerMessage::error(ErrorCode::SeatTaken, "Seat is already taken"));
+    }
+
+    let client_id = Uuid::new_v4().to_string();
+    let now = clock.now_ms() as u64;
+    let expires_at = now + locked.session_ttl_ms;
+
+    locked.id_map.insert(client_id.clone(), seat.clone());
+    locked.seat_map.insert(
+        seat.clone(),
+        ClientInfo {
+            id: client_id.clone(),
+            expires_at,
+            sender: Some(sender.clone()),
+            connected_at: now,
+            last_message_at: now,
+            ..Default::default()
+        },
+    );
+    let catch_up = phase_catch_up(&locked, clock);
+    drop(locked);
+
+    events.emit(
+        "client-connected",
+        json!({ "seat": seat, "id": client_id, "status": ClientStatus::Joined }),
+    );
+
+    joined_during_phase(&sender, joined(client_id, place, expires_at), catch_up)
+}
+
+
+/// Handle a client rejoining an existing session
+pub async fn handle_rejoin(
+    client_id: &str,
+    protocol: Option<u32>,
+    app_state: &AppState,
+    state: Arc<tokio::sync::Mutex<PerformanceState>>,
+    sender: ClientSender,
+    events: SharedEvents,
+    clock: &SharedClock,
+) -> Option<ServerMessage> {
+    if let Some(err) = ServerMessage::check_protocol(protocol) {
+        return Some(err);
+    }
+
+    let session = app_state.session_config().await;
+    let mut locked = state.lock().await;
+
+    // Retrieve seat for this client
+    let seat = match locked.id_map.get(client_id) {
+        Some(s) => s.clone(),
+        None => {
+            return Some(ServerMessage::error(
+                ErrorCode::SessionNotFound,
+                "Rejoin failed: session not found",
+            ));
+        }
+    };
+
+    // The session may have been resized since; a seat that's gone is freed
+    let place = match session.as_ref().map(|config| seats::resolve(config, &seat)) {
+        Some(Ok(place)) => place,
+        _ => {
+            locked.seat_map.remove(&seat);
+            locked.id_map.remove(client_id);
+            return Some(ServerMessage::error(ErrorCode::InvalidSeat, "This seat is no longer in the session"));
+        }
+    };
+
+    // Reconnect the seat to this socket, unless its lease already ran out
+    let info = locked.seat_map.get_mut(&seat)?;
+    let expires_at = info.expires_at;
+    let now = clock.now_ms() as u64;
+    if expires_at <= now {
+        locked.seat_map.remove(&seat);
+        locked.id_map.remove(client_id);
+        return Some(ServerMessage::error(ErrorCode::ClientIdInvalid, "Client ID is no longer valid"));
+    }
+    info.sender = Some(sender.clone());
+    info.connected_at = now;
+    info.disconnected_at = None;
+    info.last_message_at = now;
+    let status = info.status;
+    let catch_up = phase_catch_up(&locked, clock);
+    drop(locked);
+
+    events.emit("client-connected", json!({ "seat": seat, "id": client_id, "status": status }));
+
+    joined_during_phase(&sender, joined(client_id.to_string(), place, expires_at), catch_up)
+}
+
+/// Renew a client's lease for another session TTL
+pub async fn handle_refresh(
+    client_id: &str,
+    state: Arc<Mutex<PerformanceState>>,
+    clock: &SharedClock,
+) -> Option<ServerMessage> {
+    let mut locked = state.lock().await;
+    let now = clock.now_ms() as u64;
+    let expires_at = now + locked.session_ttl_ms;
+
+    let info = match locked.id_map.get(client_id).cloned() {
+        Some(seat) => locked.seat_map.get_mut(&seat),
+        None => None,
+    };
+    match info {
+        Some(info) if info.expires_at > now => {
+            info.expires_at = expires_at;
+        }
+        _ => {
+            return Some(ServerMessage::error(ErrorCode::ClientIdInvalid, "Client ID is no longer valid"));
+        }
+    }
+
+    Some(ServerMessage::Refreshed { expires_at })
+}
+
+pub async fn handle_ready(
+    app_state: Arc<AppState>,
+    client_id: &str,
+    perf_state: Arc<Mutex<PerformanceState>>,
+) -> Option<ServerMessage> {
+    let locked = perf_state.lock().await;
+    let seat = match locked.id_map.get(client_id) {
+        Some(s) => s.clone(),
+        None => {
+            return Some(ServerMessage::error(ErrorCode::ClientIdInvalid, "Client ID is no longer valid"));
+        }
+    };
+    drop(locked);
+
+    let seat_index: usize = match seat.parse() {
+        Ok(index) => index,
+        Err(_) => {
+            return Some(ServerMessage::error(ErrorCode::InvalidSeat, "Seat must be a valid number"));
+        }
+    };
+
+    let phases_map = app_state.phases.lock().await;
+
+    let id_pairs: Vec<(Option<String>, Option<String>)> = phases_map
+        .values()
+        .filter_map(|phase| {
+            phase.assignments.get(seat_index).map(|assign| {
+                (assign.rnbo_id.clone(), assign.sheet_id.clone())
+            })
+        })
+        .collect();
+
+    let rnbo_ids: HashSet<String> = id_pairs.iter().filter_map(|(r, _)| r.clone()).collect();
+    let sheet_ids: HashSet<String> = id_pairs.iter().filter_map(|(_, s)| s.clone()).collect();
+
+    println!("[ready] rnbo_ids and sheet_ids for seat {}: {:?}", seat_index, rnbo_ids);
+
+    let rnbo_lookup: HashMap<String, String> = app_state
+        .rnbo_patches
+        .lock()
+        .await
+        .iter()
+        .map(|item| (item.id.clone(), item.path.clone()))
+        .collect();
+
+    let sheet_lookup: HashMap<String, String> = app_state
+        .sheet_music
+        .lock()
+        .await
+        .iter()
+        .map(|item| (item.id.clone(), item.path.clone()))
+        .collect();
+
+    async fn manifest_entry(name: &str, path: &str) -> Option<ManifestEntry> {
+        let data = fs::read(path).await.ok()?;
+        Some(ManifestEntry {
+            name: name.to_string(),
+            size: data.len() as u64,
+            hash: hex::encode(Sha256::digest(&data)),
+        })
+    }
+
+    let mut patch_list = Vec::new();
+    for id in &rnbo_ids {
+        if let Some(path) = rnbo_lookup.get(id) {
+            if let Some(entry) = manifest_entry(id, path).await {
+                patch_list.push(entry);
+            }
+        }
+    }
+
+    let mut sheet_list = Vec::new();
+    for id in &sheet_ids {
+        if let Some(path) = sheet_lookup.get(id) {
+            if let Some(entry) = manifest_entry(id, path).await {
+                sheet_list.push(entry);
+            }
+        }
+    }
+
+    Some(ServerMessage::FileManifest {
+        seat,
+        patch_files: patch_list,
+        sheet_files: sheet_list,
+    })
+}
+
+pub async fn handle_file_request(
+    file_id: String,
+    file_type: FileType,
+    perf_state: Arc<Mutex<PerformanceState>>,
+    sender: ClientSender,
+    app_state: Arc<AppState>,
+    events: SharedEvents,
+) -> Option<ServerMessage> {
+    let lookup_vec: Vec<(String, String)> = match file_type {
+        FileType::Patch => {
+            let rnbo = app_state.rnbo_patches.lock().await.clone();
+            rnbo.into_iter().map(|item| (item.id, item.path)).collect()
+        }
+        FileType::Sheet => {
+            let sheets = app_state.sheet_music.lock().await.clone();
+            sheets.into_iter().map(|item| (item.id, item.path)).collect()
+        }
+    };
+
+    let path = match lookup_vec.into_iter().find(|(id, _)| id == &file_id) {
+        Some((_, p)) => p,
+        None => {
+            return Some(ServerMessage::error(ErrorCode::FileNotFound, "file not found"));
+        }
+    };
+
+    if sender.files_paused() {
+        return Some(ServerMessage::error(
+            ErrorCode::SlowConnection,
+            "Connection too slow for downloads; reconnect to try again",
+        ));
+    }
+
+    update_status(&perf_state, &sender, &events, ClientStatus::Downloading).await;
+
+    let sender = sender.clone();
+
+    tokio::spawn(async move {
+        let data = match fs::read(&path).await {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path, e);
+                return;
+            }
+        };
+
+        const CHUNK_SZ: usize = 64 * 1024;
+        let total_chunks = data.len().div_ceil(CHUNK_SZ);
+
+        for (i, chunk) in data.chunks(CHUNK_SZ).enumerate() {
+            let is_last = (i + 1) == total_chunks;
+            let header = ServerMessage::FileChunk {
+                id: file_id.clone(),
+                file_type,
+                is_last,
+            }
+            .to_json();
+
+            let mut buf = Vec::with_capacity(4 + header.len() + chunk.len());
+            buf.extend((header.len() as u32).to_be_bytes());
+            buf.extend(header.as_bytes());
+            buf.extend(chunk);
+            println!("Sending chunk {}, header len = {}, header = {}", i, header.len(), header);
+
+            if let Err(e) = sender.send_file(Message::binary(buf)).await {
+                eprintln!("Error sending chunk {}: {}", i, e);
+                break;
+            }
+        }
+    });
+
+    None
+}
+
+/// Handle a client reporting that every file in its manifest is stored locally.
+/// If a phase is already playing, the client gets it back so it can come in at the next bar.
+pub async fn handle_loaded(
+    client_id: &str,
+    perf_state: Arc<Mutex<PerformanceState>>,
+    events: SharedEvents,
+    clock: &SharedClock,
+) -> Option<ServerMessage> {
+    let (changed, catch_up) = {
+        let mut locked = perf_state.lock().await;
+        let seat = match locked.id_map.get(client_id) {
+            Some(s) => s.clone(),
+            None => {
+                return Some(ServerMessage::error(ErrorCode::ClientIdInvalid, "Client ID is no longer valid"));
+            }
+        };
+        let changed = locked.set_status(&seat, ClientStatus::Ready).map(|id| (seat, id));
+        (changed, phase_catch_up(&locked, clock))
+    };
+
+    if let Some((seat, id)) = changed {
+        emit_status(&events, &seat, &id, ClientStatus::Ready);
+    }
+    catch_up.map(ServerMessage::PhaseCatchUp)
+}
+
+/// Where a client joining now comes into the phase that is playing, if any
+fn phase_catch_up(state: &PerformanceState, clock: &SharedClock) -> Option<PhaseCatchUpPayload> {
+    let active = state.active_phase.as_ref()?;
+    Some(active.catch_up(clock.now_ms(), state.schedule_lead_ms()))
+}
+
+/// Move the seat behind this connection to a new status and tell the admin UI
+async fn update_status(
+    state: &Arc<Mutex<PerformanceState>>,
+    sender: &ClientSender,
+    events: &SharedEvents,
+    status: ClientStatus,
+) {
+    let changed = {
+        let mut locked = state.lock().await;
+        locked
+            .seat_for_sender(sender)
+            .and_then(|seat| locked.set_status(&seat, status).map(|id| (seat, id)))
+    };
+
+    if let Some((seat, id)) = changed {
+        emit_status(events, &seat, &id, status);
+    }
+}
+
+/// Tell the admin a seat's client is now at `status`
+pub fn emit_status(events: &SharedEvents, seat: &str, id: &str, status: ClientStatus) {
+    events.emit("client-status", json!({ "seat": seat, "id": id, "status": status }));
+}
+
+pub async fn broadcast_to_all(
+    state: Arc<Mutex<PerformanceState>>,
+    json_msg: &impl Serialize,
+) {
+    // 1. Serialize once
+    let msg = match serde_json::to_string(json_msg) {
+        Ok(s) => Message::text(s),
+        Err(e) => {
+            eprintln!("[broadcast] serialization error: {:?}", e);
+            return;
+        }
+    };
+
+    // 2. Snapshot all senders under lock
+    let senders: Vec<_> = {
+        let locked = state.lock().await;
+        locked
+            .seat_map
+            .values()
+            .filter_map(|client_info| {
+                client_info
+                    .sender
+                    .clone()
+                    .map(|sender| (client_info.id.clone(), sender))
+            })
+            .collect()
+    }; // lock is dropped here
+
+    // 3. Send to each client without holding the mutex
+    for (client_id, sender) in senders {
+        if let Err(e) = sender.send(msg.clone()) {
+            eprintln!("[broadcast] failed to send message to {}: {}", client_id, e);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::performance_types::{ActivePhase, ClientInfo, PerformanceState};
+use crate::error::{Error, Result};
+
+/// Bumped whenever the journal changes shape; older journals are ignored
+const JOURNAL_VERSION: u32 = 1;
+
+/// The broker state worth keeping across a restart: who holds which seat until when, and what
+/// is playing. Written next to the session file so clients can `rj` after the app comes back.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Journal {
+    pub version: u32,
+    pub clients: Vec<JournalClient>,
+    pub active_phase: Option<ActivePhase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalClient {
+    pub seat: String,
+    pub id: String,
+    pub expires_at: u64,
+    pub connected_at: u64,
+}
+
+/// `show.json` → `show.broker.json`, in the same directory. `None` for unsaved sessions.
+pub fn path_for(session_path: &str) -> Option<PathBuf> {
+    if session_path.trim().is_empty() {
+        return None;
+    }
+    let path = Path::new(session_path);
+    let stem = path.file_stem()?.to_string_lossy();
+    Some(path.with_file_name(format!("{}.broker.json", stem)))
+}
+
+impl Journal {
+    pub fn snapshot(state: &PerformanceState) -> Self {
+        let mut clients: Vec<JournalClient> = state
+            .seat_map
+            .iter()
+            .map(|(seat, info)| JournalClient {
+                seat: seat.clone(),
+                id: info.id.clone(),
+                expires_at: info.expires_at,
+                connected_at: info.connected_at,
+            })
+            .collect();
+        // Sorted so unchanged state compares equal and isn't rewritten
+        clients.sort_by(|a, b| a.seat.cmp(&b.seat));
+
+        Journal {
+            version: JOURNAL_VERSION,
+            clients,
+            active_phase: state.active_phase.clone(),
+        }
+    }
+
+    /// Put the journaled seats back as disconnected clients, skipping leases that ran out while
+    /// the server was down. The playing phase only comes back along with someone to play it,
+    /// so a show that ended without a stop isn't resumed days later. Returns how many seats
+    /// were restored.
+    pub fn restore(self, state: &mut PerformanceState, now: u64) -> usize {
+        let mut restored = 0;
+        for client in self.clients.into_iter().filter(|client| client.expires_at > now) {
+            state.id_map.insert(client.id.clone(), client.seat.clone());
+            state.seat_map.insert(
+                client.seat,
+                ClientInfo {
+                    id: client.id,
+                    expires_at: client.expires_at,
+                    connected_at: client.connected_at,
+                    disconnected_at: Some(now),
+                    last_message_at: now,
+                    ..Default::default()
+                },
+            );
+            restored += 1;
+        }
+        if restored > 0 {
+            state.active_phase = self.active_phase;
+        }
+        restored
+    }
+
+    /// Read a journal. A missing journal, or one from another version, is `None`.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::io(format!("Failed to read {}", path.display()), e)),
+        };
+        let journal: Journal = serde_json::from_str(&contents)?;
+        Ok(Some(journal).filter(|journal| journal.version == JOURNAL_VERSION))
+    }
+
+    /// Write through a temporary file, so a crash mid-write leaves the previous journal intact
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(&tmp, json).map_err(|e| Error::io(format!("Failed to write {}", tmp.display()), e))?;
+        fs::rename(&tmp, path).map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(expires_at: u64) -> Journal {
+        Journal {
+            version: JOURNAL_VERSION,
+            clients: vec![JournalClient {
+                seat: "3".into(),
+                id: "client".into(),
+                expires_at,
+                connected_at: 0,
+            }],
+            active_phase: Some(ActivePhase {
+                phase_id: "p1".into(),
+                bpm: 120,
+                count_in: 4,
+                start_time: 500,
+                assignments: Default::default(),
+            }),
+        }
+    }
+
+    #[test]
+    fn restores_live_seats_and_the_phase() {
+        let mut state = PerformanceState::default();
+        assert_eq!(journal(2_000).restore(&mut state, 1_000), 1);
+        assert_eq!(state.id_map.get("client").map(String::as_str), Some("3"));
+        assert!(state.seat_map["3"].sender.is_none());
+        assert!(state.active_phase.is_some());
+    }
+
+    #[test]
+    fn drops_the_phase_with_the_last_lease() {
+        let mut state = PerformanceState::default();
+        assert_eq!(journal(2_000).restore(&mut state, 3_000), 0);
+        assert!(state.seat_map.is_empty());
+        assert!(state.active_phase.is_none());
+    }
+}
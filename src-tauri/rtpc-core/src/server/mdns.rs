@@ -0,0 +1,143 @@
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+
+/// DNS-SD service type the server registers under
+pub const SERVICE_TYPE: &str = "_rtpc._tcp.local.";
+
+/// Host label used when none is configured, giving `rtpc.local`
+pub const DEFAULT_HOSTNAME: &str = "rtpc";
+
+/// DNS labels are at most 63 bytes
+const MAX_INSTANCE_LEN: usize = 63;
+
+/// How long `withdraw` waits for the goodbye packets to go out
+const WITHDRAW_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A running mDNS registration: the `.local` hostname resolving to the server's address, and a
+/// DNS-SD service named after the session
+pub struct MdnsAdvertiser {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+/// What goes into the registration
+pub struct MdnsService<'a> {
+    /// Host label without `.local`, e.g. `rtpc`
+    pub hostname: &'a str,
+    /// Usually the session name
+    pub instance: &'a str,
+    pub ip: IpAddr,
+    pub port: u16,
+    pub tls: bool,
+}
+
+/// `label` → `label.local`, as it appears in URLs
+pub fn local_hostname(label: &str) -> String {
+    format!("{}.local", label.trim_end_matches('.').trim_end_matches(".local"))
+}
+
+impl MdnsAdvertiser {
+    pub fn advertise(service: &MdnsService) -> Result<Self> {
+        let mdns_err = |e: mdns_sd::Error| Error::Mdns(e.to_string());
+        let daemon = ServiceDaemon::new().map_err(mdns_err)?;
+
+        let host = format!("{}.", local_hostname(service.hostname));
+        let instance = instance_name(service.instance);
+        let scheme = if service.tls { "https" } else { "http" };
+        let properties = [("path", "/"), ("scheme", scheme)];
+        let info = ServiceInfo::new(SERVICE_TYPE, &instance, &host, service.ip, service.port, &properties[..])
+            .map_err(mdns_err)?;
+        let fullname = info.get_fullname().to_string();
+        daemon.register(info).map_err(mdns_err)?;
+
+        println!("[mdns] advertising \"{}\" as {}:{}", instance, local_hostname(service.hostname), service.port);
+        Ok(MdnsAdvertiser { daemon, fullname })
+    }
+
+    /// Send goodbye packets for the registration and stop the responder
+    pub fn withdraw(self) {
+        if let Ok(status) = self.daemon.unregister(&self.fullname) {
+            let _ = status.recv_timeout(WITHDRAW_TIMEOUT);
+        }
+        if let Ok(status) = self.daemon.shutdown() {
+            let _ = status.recv_timeout(WITHDRAW_TIMEOUT);
+        }
+        println!("[mdns] withdrew {}", self.fullname);
+    }
+}
+
+/// Session names can contain anything; keep instance names printable and within a DNS label
+fn instance_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c == '.' || c.is_control() { ' ' } else { c })
+        .collect();
+    let trimmed = cleaned.trim();
+    let mut instance = if trimmed.is_empty() { "RTPC".to_string() } else { trimmed.to_string() };
+    while instance.len() > MAX_INSTANCE_LEN {
+        instance.pop();
+    }
+    instance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdns_sd::ServiceEvent;
+    use std::time::Instant;
+
+    const RESOLVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Wait for the browser to report something matching `pick`
+    fn wait_for<T>(events: &mdns_sd::Receiver<ServiceEvent>, pick: impl Fn(ServiceEvent) -> Option<T>) -> Option<T> {
+        let deadline = Instant::now() + RESOLVE_TIMEOUT;
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            if let Some(found) = events.recv_timeout(left).ok().and_then(&pick) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    /// Advertise, resolve it with a second responder on this machine, then withdraw
+    #[test]
+    fn advertises_and_withdraws() {
+        let ip = local_ip_address::local_ip().expect("a LAN address");
+        let label = format!("rtpc-test-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+        let service = MdnsService {
+            hostname: &label,
+            instance: "Test. Session",
+            ip,
+            port: 8443,
+            tls: true,
+        };
+
+        let browser = ServiceDaemon::new().expect("browser daemon");
+        let events = browser.browse(SERVICE_TYPE).expect("browse");
+        let advertiser = MdnsAdvertiser::advertise(&service).expect("advertise");
+        let fullname = advertiser.fullname.clone();
+        assert_eq!(fullname, format!("Test  Session.{}", SERVICE_TYPE));
+
+        let resolved = wait_for(&events, |event| match event {
+            ServiceEvent::ServiceResolved(info) if info.get_fullname() == fullname => Some(info),
+            _ => None,
+        })
+        .expect("service resolves");
+        assert_eq!(resolved.get_hostname(), format!("{}.local.", label));
+        assert_eq!(resolved.get_port(), 8443);
+        assert!(resolved.get_addresses().contains(&ip));
+        assert_eq!(resolved.get_property_val_str("scheme"), Some("https"));
+
+        advertiser.withdraw();
+        let removed = wait_for(&events, |event| match event {
+            ServiceEvent::ServiceRemoved(_, name) if name == fullname => Some(()),
+            _ => None,
+        });
+        assert!(removed.is_some(), "service removed after withdraw");
+
+        let _ = browser.shutdown();
+    }
+}
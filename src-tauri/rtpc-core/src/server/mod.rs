@@ -0,0 +1,340 @@
+pub mod server_controller;
+pub mod performance_types;
+pub mod handlers;
+pub mod clock;
+pub mod time_sync;
+pub mod protocol;
+pub mod events;
+pub mod tls;
+pub mod network;
+pub mod qr;
+pub mod mdns;
+pub mod access;
+pub mod seats;
+pub mod journal;
+pub mod outbox;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use ts_rs::TS;
+
+use self::access::JoinAccess;
+use self::outbox::SlowClientPolicy;
+use self::seats::{SeatAllocation, SeatAllocator};
+use self::tls::TlsSource;
+use crate::error::{Error, Result};
+
+/// Default margin added on top of the worst client RTT when scheduling cues
+pub const DEFAULT_SAFETY_MS: u64 = 250;
+
+/// Largest safety margin accepted. More than a minute is a typo, not a margin.
+pub const MAX_SAFETY_MS: u64 = 60_000;
+
+/// Default interval between drift-corrected offset updates
+pub const DEFAULT_RESYNC_INTERVAL_MS: u64 = 30_000;
+
+/// Which listeners the broker opens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// WSS/HTTPS only
+    #[default]
+    Tls,
+    /// Plain WS/HTTP only, for localhost and wired rehearsal setups
+    Plain,
+    /// TLS on `wsPort` and plain on `plainPort`
+    Both,
+}
+
+impl FromStr for Transport {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "tls" => Ok(Transport::Tls),
+            "plain" => Ok(Transport::Plain),
+            "both" => Ok(Transport::Both),
+            _ => Err(format!("Unknown transport `{}` (tls, plain or both)", s)),
+        }
+    }
+}
+
+/// Where the server listens, worked out by [`ServerOptions::listen`]
+#[derive(Debug, Clone)]
+pub struct Listen {
+    /// Address to bind, `0.0.0.0` or `::` for all interfaces
+    pub bind_ip: IpAddr,
+    /// Address put in join URLs and the self-signed certificate
+    pub advertise_ip: IpAddr,
+    pub tls_port: Option<u16>,
+    pub plain_port: Option<u16>,
+    /// Plain HTTP port that redirects to `tls_port`
+    pub redirect_port: Option<u16>,
+    /// Host label to advertise over mDNS as `<label>.local`, if advertising
+    pub mdns_hostname: Option<String>,
+    /// Put the `.local` hostname in join URLs instead of the IP
+    pub join_by_hostname: bool,
+}
+
+/// How to run the broker, as sent by the admin UI
+#[derive(Debug, Clone, PartialEq, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerOptions {
+    /// 0 picks a free port, which `start_server` reports back
+    pub ws_port: u16,
+    #[ts(type = "number")]
+    pub ttl_ms: u64,
+    #[ts(optional, as = "Option<u32>")]
+    pub safety_ms: Option<u64>,
+    #[ts(optional, as = "Option<u32>")]
+    pub resync_interval_ms: Option<u64>,
+    /// PEM certificate and key. Without them a self-signed certificate is used.
+    #[ts(optional)]
+    pub cert_path: Option<String>,
+    #[ts(optional)]
+    pub key_path: Option<String>,
+    #[serde(default)]
+    pub transport: Transport,
+    /// Plain WS/HTTP port when `transport` is `both`
+    #[ts(optional)]
+    pub plain_port: Option<u16>,
+    /// Answer plain HTTP on this port with a redirect to HTTPS
+    #[ts(optional)]
+    pub redirect_port: Option<u16>,
+    /// IP of the interface to listen on (IPv4 or IPv6). All IPv4 interfaces if unset.
+    #[ts(optional)]
+    pub bind_address: Option<String>,
+    /// IP to put in the join URL. Defaults to the bound address, or the main LAN address
+    /// when listening on all interfaces.
+    #[ts(optional)]
+    pub advertise_address: Option<String>,
+    /// Advertise the server over mDNS (on by default)
+    #[ts(optional)]
+    pub mdns: Option<bool>,
+    /// mDNS host label, giving `<label>.local`. Defaults to `rtpc`.
+    #[ts(optional)]
+    pub mdns_hostname: Option<String>,
+    /// Put the `.local` hostname in the join URL and QR codes instead of the IP
+    #[ts(optional)]
+    pub join_by_hostname: Option<bool>,
+    /// Code the audience has to enter to take a seat
+    #[ts(optional)]
+    pub join_code: Option<String>,
+    /// Only admit holders of a seat's QR ticket
+    #[ts(optional)]
+    pub require_seat_tokens: Option<bool>,
+    /// How seats are picked for clients that join with `any`
+    #[ts(optional)]
+    pub seat_allocation: Option<SeatAllocation>,
+    /// Seed for `random` seat allocation, to repeat a run. Random if unset.
+    #[ts(optional, as = "Option<u32>")]
+    pub seat_seed: Option<u64>,
+    /// What happens to clients that fall too far behind on their messages (disconnect by default)
+    #[ts(optional)]
+    pub slow_clients: Option<SlowClientPolicy>,
+}
+
+impl ServerOptions {
+    pub fn safety_ms(&self) -> u64 {
+        self.safety_ms.unwrap_or(DEFAULT_SAFETY_MS)
+    }
+
+    pub fn resync_interval(&self) -> Duration {
+        Duration::from_millis(self.resync_interval_ms.unwrap_or(DEFAULT_RESYNC_INTERVAL_MS).max(1))
+    }
+
+    /// Work out the addresses and ports for the chosen transport, rejecting combinations that can't work
+    pub fn listen(&self) -> Result<Listen> {
+        if self.safety_ms() > MAX_SAFETY_MS {
+            return Err(Error::InvalidOptions(format!(
+                "The safety margin can be at most {} ms",
+                MAX_SAFETY_MS
+            )));
+        }
+
+        let (tls_port, plain_port) = match self.transport {
+            Transport::Tls => (Some(self.ws_port), None),
+            Transport::Plain => (None, Some(self.ws_port)),
+            Transport::Both => {
+                let plain_port = self.plain_port.ok_or_else(|| {
+                    Error::InvalidOptions("Serving both TLS and plain needs a plain port".into())
+                })?;
+                (Some(self.ws_port), Some(plain_port))
+            }
+        };
+
+        let redirect_port = match self.redirect_port {
+            Some(_) if tls_port.is_none() => {
+                return Err(Error::InvalidOptions("Redirecting to HTTPS needs TLS".into()))
+            }
+            port => port,
+        };
+
+        let ports = [tls_port, plain_port, redirect_port];
+        // Port 0 asks the OS for a free port, so several of those don't clash
+        let used: Vec<u16> = ports.iter().flatten().copied().filter(|&port| port != 0).collect();
+        if (1..used.len()).any(|i| used[..i].contains(&used[i])) {
+            return Err(Error::InvalidOptions("Each listener needs its own port".into()));
+        }
+
+        let bind_ip = match &self.bind_address {
+            Some(address) => parse_ip(address)?,
+            None => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        };
+        let advertise_ip = match &self.advertise_address {
+            Some(address) => parse_ip(address)?,
+            None if bind_ip.is_unspecified() => network::default_address(),
+            None => bind_ip,
+        };
+
+        let mdns_hostname = if self.mdns.unwrap_or(true) {
+            let label = self.mdns_hostname.as_deref().unwrap_or(mdns::DEFAULT_HOSTNAME);
+            Some(parse_host_label(label)?)
+        } else {
+            None
+        };
+        let join_by_hostname = self.join_by_hostname.unwrap_or(false);
+        if join_by_hostname && mdns_hostname.is_none() {
+            return Err(Error::InvalidOptions("Joining by hostname needs mDNS".into()));
+        }
+
+        Ok(Listen {
+            bind_ip,
+            advertise_ip,
+            tls_port,
+            plain_port,
+            redirect_port,
+            mdns_hostname,
+            join_by_hostname,
+        })
+    }
+
+    /// Join rules, signing seat tickets with the session's `seat_secret`
+    pub fn join_access(&self, seat_secret: Option<&str>) -> JoinAccess {
+        JoinAccess::new(
+            self.join_code.clone(),
+            self.require_seat_tokens.unwrap_or(false),
+            seat_secret,
+        )
+    }
+
+    /// Seat picker for `any` joins
+    pub fn seat_allocator(&self) -> SeatAllocator {
+        let seed = self.seat_seed.unwrap_or_else(|| uuid::Uuid::new_v4().as_u64_pair().0);
+        SeatAllocator::new(self.seat_allocation.unwrap_or_default(), seed)
+    }
+
+    /// Certificate source, generating self-signed certificates into `generated_dir` if needed
+    pub fn tls_source(&self, generated_dir: PathBuf) -> TlsSource {
+        TlsSource {
+            cert_path: self.cert_path.as_ref().map(PathBuf::from),
+            key_path: self.key_path.as_ref().map(PathBuf::from),
+            generated_dir,
+        }
+    }
+}
+
+fn parse_ip(address: &str) -> Result<IpAddr> {
+    address
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .map_err(|_| Error::InvalidOptions(format!("`{}` is not an IP address", address)))
+}
+
+/// A single DNS label: letters, digits and inner hyphens. `.local` is added later.
+fn parse_host_label(label: &str) -> Result<String> {
+    let label = label.trim().trim_end_matches(".local").to_ascii_lowercase();
+    let valid = !label.is_empty()
+        && label.len() <= 63
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if valid {
+        Ok(label)
+    } else {
+        Err(Error::InvalidOptions(format!("`{}` is not a valid hostname", label)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(transport: Transport) -> ServerOptions {
+        ServerOptions {
+            ws_port: 8443,
+            ttl_ms: 3_600_000,
+            safety_ms: None,
+            resync_interval_ms: None,
+            cert_path: None,
+            key_path: None,
+            transport,
+            plain_port: None,
+            redirect_port: None,
+            bind_address: None,
+            advertise_address: Some("192.0.2.10".into()),
+            mdns: Some(false),
+            mdns_hostname: None,
+            join_by_hostname: None,
+            join_code: None,
+            require_seat_tokens: None,
+            seat_allocation: None,
+            seat_seed: None,
+            slow_clients: None,
+        }
+    }
+
+    #[test]
+    fn rejects_an_absurd_safety_margin() {
+        let mut opts = options(Transport::Tls);
+        opts.safety_ms = Some(MAX_SAFETY_MS);
+        assert!(opts.listen().is_ok());
+        opts.safety_ms = Some(MAX_SAFETY_MS + 1);
+        assert!(matches!(opts.listen(), Err(Error::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn plain_only_serves_the_ws_port_without_tls() {
+        let listen = options(Transport::Plain).listen().unwrap();
+        assert_eq!((listen.tls_port, listen.plain_port), (None, Some(8443)));
+        assert_eq!(listen.advertise_ip, "192.0.2.10".parse::<IpAddr>().unwrap());
+
+        let mut redirected = options(Transport::Plain);
+        redirected.redirect_port = Some(8080);
+        assert!(matches!(redirected.listen(), Err(Error::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn both_transports_need_a_second_port() {
+        let mut opts = options(Transport::Both);
+        assert!(matches!(opts.listen(), Err(Error::InvalidOptions(_))));
+
+        opts.plain_port = Some(8443);
+        assert!(matches!(opts.listen(), Err(Error::InvalidOptions(_))));
+
+        opts.plain_port = Some(8080);
+        let listen = opts.listen().unwrap();
+        assert_eq!((listen.tls_port, listen.plain_port), (Some(8443), Some(8080)));
+    }
+
+    #[test]
+    fn the_redirect_needs_its_own_port() {
+        let mut opts = options(Transport::Tls);
+        opts.redirect_port = Some(8443);
+        assert!(matches!(opts.listen(), Err(Error::InvalidOptions(_))));
+
+        opts.redirect_port = Some(80);
+        assert_eq!(opts.listen().unwrap().redirect_port, Some(80));
+
+        // Any number of listeners can ask the OS for a port
+        opts.ws_port = 0;
+        opts.redirect_port = Some(0);
+        assert!(opts.listen().is_ok());
+    }
+}
@@ -0,0 +1,51 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr};
+use ts_rs::TS;
+
+use crate::error::{Error, Result};
+
+/// One address of a network adapter, for choosing where to listen and what to put in the QR code
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub address: String,
+    pub ipv6: bool,
+    pub loopback: bool,
+}
+
+/// Every usable address on this machine, IPv4 first.
+/// IPv6 link-local addresses are left out: phones can't open them without a zone ID.
+pub fn list_interfaces() -> Result<Vec<NetworkInterface>> {
+    let mut interfaces: Vec<NetworkInterface> = local_ip_address::list_afinet_netifas()
+        .map_err(|e| Error::Network(e.to_string()))?
+        .into_iter()
+        .filter(|(_, ip)| !is_ipv6_link_local(ip))
+        .map(|(name, ip)| NetworkInterface {
+            name,
+            address: ip.to_string(),
+            ipv6: ip.is_ipv6(),
+            loopback: ip.is_loopback(),
+        })
+        .collect();
+
+    interfaces.sort_by_key(|interface| (interface.loopback, interface.ipv6));
+    Ok(interfaces)
+}
+
+/// The address to advertise when listening on all interfaces
+pub fn default_address() -> IpAddr {
+    local_ip_address::local_ip().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+/// `ip` as it goes into a URL, with brackets around IPv6
+pub fn url_host(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{}]", ip),
+    }
+}
+
+fn is_ipv6_link_local(ip: &IpAddr) -> bool {
+    matches!(ip, IpAddr::V6(ip) if (ip.segments()[0] & 0xffc0) == 0xfe80)
+}
@@ -0,0 +1,278 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Notify;
+use ts_rs::TS;
+use warp::ws::Message;
+
+use crate::error::{Error, Result};
+
+/// Control and timing messages a client may have queued. They are small, so a full lane means
+/// the client has stopped reading.
+const CONTROL_QUEUE: usize = 256;
+
+/// File chunks a client may have queued: 16 × 64 KiB, about 1 MiB per client
+const FILE_QUEUE: usize = 16;
+
+/// How long a file chunk may wait for room before the client counts as lagging
+const FILE_STALL: Duration = Duration::from_secs(10);
+
+/// What happens to a client that falls too far behind on its messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowClientPolicy {
+    /// Close the connection. The seat is kept, so the client can rejoin with `rj` and catch up.
+    #[default]
+    Disconnect,
+    /// Stop sending it file data for the rest of the connection; control messages still go out
+    PauseFiles,
+    /// Only tell the admin. File data waits for room and control messages that don't fit are dropped.
+    Warn,
+}
+
+impl SlowClientPolicy {
+    /// Whether a lagging client's connection is closed; otherwise it stays connected
+    pub fn disconnects(self) -> bool {
+        self == SlowClientPolicy::Disconnect
+    }
+}
+
+impl FromStr for SlowClientPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "disconnect" => Ok(SlowClientPolicy::Disconnect),
+            "pause-files" | "pause_files" => Ok(SlowClientPolicy::PauseFiles),
+            "warn" => Ok(SlowClientPolicy::Warn),
+            _ => Err(format!("Unknown slow client policy `{}` (disconnect, pause-files or warn)", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    policy: SlowClientPolicy,
+    lagging: AtomicBool,
+    lagged: Notify,
+}
+
+/// The sending half of a client connection. Control and timing messages have their own lane,
+/// which the writer always empties before sending more file data, so a `phase_start` never
+/// waits behind a download.
+#[derive(Debug, Clone)]
+pub struct ClientSender {
+    control: mpsc::Sender<Message>,
+    files: mpsc::Sender<Message>,
+    shared: Arc<Shared>,
+}
+
+/// The receiving half, drained by the connection's writer task
+#[derive(Debug)]
+pub struct ClientReceiver {
+    control: mpsc::Receiver<Message>,
+    files: mpsc::Receiver<Message>,
+}
+
+pub fn channel(policy: SlowClientPolicy) -> (ClientSender, ClientReceiver) {
+    let (control_tx, control_rx) = mpsc::channel(CONTROL_QUEUE);
+    let (files_tx, files_rx) = mpsc::channel(FILE_QUEUE);
+    let shared = Arc::new(Shared {
+        policy,
+        lagging: AtomicBool::new(false),
+        lagged: Notify::new(),
+    });
+    (
+        ClientSender { control: control_tx, files: files_tx, shared },
+        ClientReceiver { control: control_rx, files: files_rx },
+    )
+}
+
+impl ClientSender {
+    /// Queue a control or timing message. Never waits: if the lane is full, the message is
+    /// dropped and the client is reported as lagging.
+    pub fn send(&self, msg: impl Into<Message>) -> Result<()> {
+        match self.control.try_send(msg.into()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.lag();
+                Err(Error::ClientLagging)
+            }
+            Err(TrySendError::Closed(_)) => Err(Error::ConnectionClosed),
+        }
+    }
+
+    /// Queue a file chunk, waiting for room. A client that leaves no room for [`FILE_STALL`] is
+    /// reported as lagging; unless the policy is to only warn, the chunk is then given up.
+    pub async fn send_file(&self, chunk: Message) -> Result<()> {
+        if self.files_paused() {
+            return Err(Error::ClientLagging);
+        }
+        let send = self.files.send(chunk);
+        tokio::pin!(send);
+        loop {
+            match tokio::time::timeout(FILE_STALL, &mut send).await {
+                Ok(sent) => return sent.map_err(|_| Error::ConnectionClosed),
+                Err(_) => {
+                    self.lag();
+                    if self.shared.policy != SlowClientPolicy::Warn {
+                        return Err(Error::ClientLagging);
+                    }
+                }
+            }
+        }
+    }
+
+    /// File data is no longer sent on this connection
+    pub fn files_paused(&self) -> bool {
+        self.shared.policy == SlowClientPolicy::PauseFiles && self.shared.lagging.load(Ordering::Relaxed)
+    }
+
+    pub fn policy(&self) -> SlowClientPolicy {
+        self.shared.policy
+    }
+
+    /// Resolves once the client first falls behind
+    pub async fn lagged(&self) {
+        self.shared.lagged.notified().await
+    }
+
+    /// Whether both senders lead to the same connection
+    pub fn same_channel(&self, other: &ClientSender) -> bool {
+        self.control.same_channel(&other.control)
+    }
+
+    fn lag(&self) {
+        if !self.shared.lagging.swap(true, Ordering::Relaxed) {
+            // Stores a permit, so the connection hears about it even if it isn't waiting yet
+            self.shared.lagged.notify_one();
+        }
+    }
+}
+
+impl ClientReceiver {
+    /// The next message to write, control messages first. `None` once every sender is gone.
+    pub async fn recv(&mut self) -> Option<Message> {
+        tokio::select! {
+            biased;
+            Some(msg) = self.control.recv() => Some(msg),
+            Some(msg) = self.files.recv() => Some(msg),
+            else => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(msg: &Message) -> &str {
+        msg.to_str().expect("text message")
+    }
+
+    /// Fill the file lane so the next chunk has to wait
+    async fn fill_files(sender: &ClientSender) {
+        for i in 0..FILE_QUEUE {
+            sender.send_file(Message::text(format!("chunk {}", i))).await.expect("room for a chunk");
+        }
+    }
+
+    #[test]
+    fn only_disconnect_drops_the_connection() {
+        assert!(SlowClientPolicy::Disconnect.disconnects());
+        assert!(!SlowClientPolicy::PauseFiles.disconnects());
+        assert!(!SlowClientPolicy::Warn.disconnects());
+    }
+
+    #[tokio::test]
+    async fn control_messages_overtake_queued_files() {
+        let (sender, mut receiver) = channel(SlowClientPolicy::Disconnect);
+        sender.send_file(Message::text("chunk 0")).await.unwrap();
+        sender.send_file(Message::text("chunk 1")).await.unwrap();
+        sender.send(Message::text("phase_start")).unwrap();
+
+        assert_eq!(text(&receiver.recv().await.unwrap()), "phase_start");
+        assert_eq!(text(&receiver.recv().await.unwrap()), "chunk 0");
+        sender.send(Message::text("pong")).unwrap();
+        assert_eq!(text(&receiver.recv().await.unwrap()), "pong");
+        assert_eq!(text(&receiver.recv().await.unwrap()), "chunk 1");
+
+        drop(sender);
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_full_control_lane_reports_lagging() {
+        let (sender, _receiver) = channel(SlowClientPolicy::Disconnect);
+        for _ in 0..CONTROL_QUEUE {
+            sender.send(Message::text("offset_update")).unwrap();
+        }
+        assert!(matches!(sender.send(Message::text("phase_start")), Err(Error::ClientLagging)));
+        // The permit is stored, so a connection that starts waiting later still hears about it
+        tokio::time::timeout(Duration::from_secs(1), sender.lagged())
+            .await
+            .expect("lag reported");
+        // Lagging on control messages doesn't pause files
+        assert!(!sender.files_paused());
+    }
+
+    #[tokio::test]
+    async fn a_closed_connection_is_not_lagging() {
+        let (sender, receiver) = channel(SlowClientPolicy::Disconnect);
+        drop(receiver);
+        assert!(matches!(sender.send(Message::text("pong")), Err(Error::ConnectionClosed)));
+        assert!(matches!(sender.send_file(Message::text("chunk")).await, Err(Error::ConnectionClosed)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_stalled_download_gives_up_when_disconnecting() {
+        let (sender, _receiver) = channel(SlowClientPolicy::Disconnect);
+        fill_files(&sender).await;
+        assert!(matches!(sender.send_file(Message::text("stuck")).await, Err(Error::ClientLagging)));
+        sender.lagged().await;
+        assert!(!sender.files_paused());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_files_stops_only_the_file_lane() {
+        let (sender, mut receiver) = channel(SlowClientPolicy::PauseFiles);
+        fill_files(&sender).await;
+        assert!(matches!(sender.send_file(Message::text("stuck")).await, Err(Error::ClientLagging)));
+        assert!(sender.files_paused());
+
+        // Even with room again, no more file data goes out on this connection
+        assert_eq!(text(&receiver.recv().await.unwrap()), "chunk 0");
+        assert!(matches!(sender.send_file(Message::text("later")).await, Err(Error::ClientLagging)));
+        // Control messages still do
+        sender.send(Message::text("phase_start")).unwrap();
+        assert_eq!(text(&receiver.recv().await.unwrap()), "phase_start");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn warn_keeps_waiting_for_room() {
+        let (sender, mut receiver) = channel(SlowClientPolicy::Warn);
+        fill_files(&sender).await;
+
+        let waiting = tokio::spawn({
+            let sender = sender.clone();
+            async move { sender.send_file(Message::text("patient")).await }
+        });
+        // Past the stall the admin is told, but the chunk keeps waiting
+        sender.lagged().await;
+        tokio::time::sleep(FILE_STALL * 3).await;
+        assert!(!waiting.is_finished());
+        assert!(!sender.files_paused());
+
+        for i in 0..FILE_QUEUE {
+            assert_eq!(text(&receiver.recv().await.unwrap()), format!("chunk {}", i));
+        }
+        waiting.await.unwrap().expect("sent once there is room");
+        assert_eq!(text(&receiver.recv().await.unwrap()), "patient");
+        sender.send(Message::text("pong")).unwrap();
+        assert_eq!(text(&receiver.recv().await.unwrap()), "pong");
+    }
+}
@@ -0,0 +1,495 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use ts_rs::TS;
+
+use crate::access::JoinAccess;
+use crate::outbox::{ClientSender, SlowClientPolicy};
+use crate::seats::SeatAllocator;
+use crate::error::Error;
+use crate::time_sync::SyncStats;
+use crate::state::{AssignmentPayload, PhaseCatchUpPayload, PhaseStartPayload};
+
+/// Bar length when a phase has no count-in to go by
+const DEFAULT_BEATS_PER_BAR: u32 = 4;
+
+/// RTTs above this are left out of the scheduling lead. Waiting can't help a phone that slow,
+/// and a bogus report must not push every cue far into the future.
+const MAX_LEAD_RTT_MS: u64 = 5_000;
+
+
+/// How far a client has come through join → sync → download → ready
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, TS, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientStatus {
+    #[default]
+    Joined,
+    Synced,
+    Downloading,
+    Ready,
+}
+
+#[derive(Debug, Default)]
+pub struct ClientInfo {
+    pub id: String,
+    pub expires_at: u64,
+    pub sync: SyncStats,
+    pub sender: Option<ClientSender>, // None while disconnected
+    pub connected_at: u64,
+    pub disconnected_at: Option<u64>,
+    pub last_message_at: u64,
+    pub status: ClientStatus,
+}
+
+/// Snapshot of one seat for the admin roster
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+pub struct ClientSummary {
+    pub seat: String,
+    pub client_id: String,
+    pub connected: bool,
+    #[ts(type = "number")]
+    pub connected_at: u64,
+    #[ts(type = "number | null")]
+    pub disconnected_at: Option<u64>,
+    #[ts(type = "number")]
+    pub expires_at: u64,
+    #[ts(type = "number")]
+    pub last_message_at: u64,
+    pub status: ClientStatus,
+    /// Round trip of the latest time sync
+    #[ts(type = "number | null")]
+    pub last_rtt_ms: Option<u64>,
+    /// Fastest round trip kept, the one `offset_ms` is taken from
+    #[ts(type = "number | null")]
+    pub best_rtt_ms: Option<u64>,
+    pub offset_ms: Option<f64>,
+    pub jitter_ms: Option<f64>,
+    pub skew_ppm: Option<f64>,
+    pub sync_samples: usize,
+}
+
+/// The phase currently playing on the clients, if any
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivePhase {
+    pub phase_id: String,
+    pub bpm: u32,
+    pub count_in: u32,
+    pub start_time: i64, // server clock, ms
+    pub assignments: HashMap<String, AssignmentPayload>, // seat -> files
+}
+
+impl ActivePhase {
+    /// Length of one beat in milliseconds
+    pub fn beat_ms(&self) -> f64 {
+        60_000.0 / self.bpm.max(1) as f64
+    }
+
+    /// The count-in is taken to be one bar
+    pub fn beats_per_bar(&self) -> u32 {
+        if self.count_in > 0 {
+            self.count_in
+        } else {
+            DEFAULT_BEATS_PER_BAR
+        }
+    }
+
+    /// Server time of the downbeat, after the count-in
+    fn beat_zero(&self) -> f64 {
+        self.start_time as f64 + self.count_in as f64 * self.beat_ms()
+    }
+
+    /// Beats since the downbeat at `t`; negative during the count-in
+    pub fn beat_at(&self, t: i64) -> f64 {
+        (t as f64 - self.beat_zero()) / self.beat_ms()
+    }
+
+    /// Time of beat `beat` (server clock, ms)
+    pub fn time_of_beat(&self, beat: i64) -> i64 {
+        (self.beat_zero() + beat as f64 * self.beat_ms()).round() as i64
+    }
+
+    /// First beat boundary at or after `t` (server clock, ms)
+    pub fn next_beat_at(&self, t: i64) -> i64 {
+        self.time_of_beat(self.beat_at(t).ceil() as i64)
+    }
+
+    /// Beat number of the first bar line at or after `t`, counting from the downbeat. Never
+    /// before the downbeat.
+    pub fn next_bar_at(&self, t: i64) -> i64 {
+        let bar = self.beats_per_bar() as f64;
+        ((self.beat_at(t) / bar).ceil() * bar).max(0.0) as i64
+    }
+
+    /// What `phase_start` sent to everyone
+    pub fn start_payload(&self) -> PhaseStartPayload {
+        PhaseStartPayload {
+            bpm: self.bpm,
+            count_in: self.count_in,
+            start_time: self.start_time,
+            assignments: self.assignments.clone(),
+        }
+    }
+
+    /// The phase as it stands at `now`, entering on the first bar at least `lead_ms` away
+    pub fn catch_up(&self, now: i64, lead_ms: i64) -> PhaseCatchUpPayload {
+        let entry_beat = self.next_bar_at(now + lead_ms);
+        PhaseCatchUpPayload {
+            phase: self.start_payload(),
+            current_beat: self.beat_at(now),
+            server_time: now,
+            entry_beat,
+            entry_time: self.time_of_beat(entry_beat),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PerformanceState {
+    pub active_phase: Option<ActivePhase>,
+    pub session_ttl_ms: u64,
+    pub safety_ms: u64, // added on top of the worst RTT when scheduling cues
+
+    pub seat_map: HashMap<String, ClientInfo>, // seat -> client info
+    pub id_map: HashMap<String, String>,       // client_id -> seat
+
+    pub access: JoinAccess, // join code and seat tickets
+    pub allocator: SeatAllocator, // picks seats for `any` joins
+    pub slow_clients: SlowClientPolicy, // what happens to clients that fall behind
+}
+
+impl PerformanceState {
+    /// Forget every client and the playing phase, keeping the configuration for the next start
+    pub fn reset(&mut self) {
+        self.active_phase = None;
+        self.seat_map.clear();
+        self.id_map.clear();
+    }
+
+    /// Find the seat whose connection uses this sender
+    pub fn seat_for_sender(&self, sender: &ClientSender) -> Option<String> {
+        self.seat_map
+            .iter()
+            .find(|(_, info)| info.sender.as_ref().is_some_and(|s| s.same_channel(sender)))
+            .map(|(seat, _)| seat.clone())
+    }
+
+    /// Record that the connection behind this sender just sent a message
+    pub fn touch(&mut self, sender: &ClientSender, now: u64) {
+        if let Some(seat) = self.seat_for_sender(sender) {
+            if let Some(info) = self.seat_map.get_mut(&seat) {
+                info.last_message_at = now;
+            }
+        }
+    }
+
+    /// Set a seat's status. Returns the client ID if the status changed.
+    pub fn set_status(&mut self, seat: &str, status: ClientStatus) -> Option<String> {
+        let info = self.seat_map.get_mut(seat)?;
+        if info.status == status {
+            return None;
+        }
+        info.status = status;
+        Some(info.id.clone())
+    }
+
+    /// Roster of every taken seat, sorted by seat
+    pub fn client_summaries(&self) -> Vec<ClientSummary> {
+        let mut clients: Vec<ClientSummary> = self
+            .seat_map
+            .iter()
+            .map(|(seat, info)| ClientSummary {
+                seat: seat.clone(),
+                client_id: info.id.clone(),
+                connected: info.sender.is_some(),
+                connected_at: info.connected_at,
+                disconnected_at: info.disconnected_at,
+                expires_at: info.expires_at,
+                last_message_at: info.last_message_at,
+                status: info.status,
+                last_rtt_ms: info.sync.last_rtt_ms(),
+                best_rtt_ms: info.sync.best_rtt_ms(),
+                offset_ms: info.sync.offset_ms(),
+                jitter_ms: info.sync.jitter_ms(),
+                skew_ppm: info.sync.clock_model().map(|m| m.skew_ppm),
+                sync_samples: info.sync.sample_count(),
+            })
+            .collect();
+        clients.sort_by(|a, b| {
+            match (a.seat.parse::<usize>(), b.seat.parse::<usize>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                _ => a.seat.cmp(&b.seat),
+            }
+        });
+        clients
+    }
+
+    /// Drop the sender of whichever seat uses this connection and record when it went away.
+    /// Returns the seat and client ID, if the connection had joined.
+    pub fn mark_disconnected(
+        &mut self,
+        sender: &ClientSender,
+        now: u64,
+    ) -> Option<(String, String)> {
+        let seat = self.seat_for_sender(sender)?;
+        let info = self.seat_map.get_mut(&seat)?;
+        info.sender = None;
+        info.disconnected_at = Some(now);
+        Some((seat, info.id.clone()))
+    }
+
+    /// Free a seat and forget its client
+    pub fn remove_seat(&mut self, seat: &str) -> Option<ClientInfo> {
+        let info = self.seat_map.remove(seat)?;
+        self.id_map.remove(&info.id);
+        Some(info)
+    }
+
+    /// Exchange the clients in two seats; either may be empty, but not both.
+    /// Returns the (client ID, new seat) of every client that moved.
+    pub fn swap_seats(&mut self, a: &str, b: &str) -> crate::Result<Vec<(String, String)>> {
+        if !self.seat_map.contains_key(a) && !self.seat_map.contains_key(b) {
+            return Err(Error::SeatEmpty(a.to_string()));
+        }
+        if a == b {
+            return Ok(Vec::new());
+        }
+        let first = self.seat_map.remove(a);
+        let second = self.seat_map.remove(b);
+
+        let mut moved = Vec::new();
+        for (info, seat) in [(first, b), (second, a)] {
+            let Some(mut info) = info else { continue };
+            // The new seat has its own files, so the client has to check its manifest again
+            if matches!(info.status, ClientStatus::Downloading | ClientStatus::Ready) {
+                info.status = ClientStatus::Synced;
+            }
+            self.id_map.insert(info.id.clone(), seat.to_string());
+            moved.push((info.id.clone(), seat.to_string()));
+            self.seat_map.insert(seat.to_string(), info);
+        }
+        Ok(moved)
+    }
+
+    /// Move a client to an empty seat. Returns the seat it left.
+    pub fn move_client(&mut self, client_id: &str, to: &str) -> crate::Result<String> {
+        let from = self
+            .id_map
+            .get(client_id)
+            .cloned()
+            .ok_or_else(|| Error::ClientNotFound(client_id.to_string()))?;
+        if from == to {
+            return Ok(from);
+        }
+        if self.seat_map.contains_key(to) {
+            return Err(Error::SeatTaken(to.to_string()));
+        }
+        self.swap_seats(&from, to)?;
+        Ok(from)
+    }
+
+    /// Remove every client whose lease ended before `now` and free its seat
+    pub fn expire_clients(&mut self, now: u64) -> Vec<(String, ClientInfo)> {
+        let expired: Vec<String> = self
+            .seat_map
+            .iter()
+            .filter(|(_, info)| info.expires_at <= now)
+            .map(|(seat, _)| seat.clone())
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|seat| self.remove_seat(&seat).map(|info| (seat, info)))
+            .collect()
+    }
+
+    /// Worst recent RTT among the connected seats. A seat slower than [`MAX_LEAD_RTT_MS`] is
+    /// left out, and logged, rather than delaying everyone.
+    pub fn worst_rtt_ms(&self) -> u64 {
+        let mut worst = 0;
+        for (seat, info) in &self.seat_map {
+            let Some(rtt) = info.sender.as_ref().and(info.sync.recent_max_rtt_ms()) else {
+                continue;
+            };
+            if rtt > MAX_LEAD_RTT_MS {
+                println!("[schedule] seat {} left out of the lead, its RTT is {} ms", seat, rtt);
+            } else {
+                worst = worst.max(rtt);
+            }
+        }
+        worst
+    }
+
+    /// How far ahead a cue must be scheduled so the slowest client still gets it in time
+    pub fn schedule_lead_ms(&self) -> i64 {
+        let lead = self.worst_rtt_ms().saturating_add(self.safety_ms);
+        i64::try_from(lead).unwrap_or(i64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbox::{self, ClientReceiver};
+    use crate::time_sync::TimeSample;
+
+    const NOW: i64 = 1_800_000_000_000;
+
+    /// 120 bpm (500 ms beats) with a one-bar count-in: the downbeat is at 12 000
+    fn phase() -> ActivePhase {
+        ActivePhase {
+            phase_id: "p1".into(),
+            bpm: 120,
+            count_in: 4,
+            start_time: 10_000,
+            assignments: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn next_beat_is_on_the_grid() {
+        let phase = phase();
+        assert_eq!(phase.next_beat_at(12_000), 12_000);
+        assert_eq!(phase.next_beat_at(12_001), 12_500);
+        assert_eq!(phase.next_beat_at(12_499), 12_500);
+        // Count-in beats are on the same grid
+        assert_eq!(phase.next_beat_at(10_200), 10_500);
+    }
+
+    #[test]
+    fn next_bar_counts_from_the_downbeat() {
+        let phase = phase();
+        assert_eq!(phase.next_bar_at(12_000), 0);
+        assert_eq!(phase.next_bar_at(12_100), 4);
+        assert_eq!(phase.next_bar_at(14_000), 4);
+        assert_eq!(phase.next_bar_at(14_001), 8);
+        // Never before the downbeat, even during the count-in
+        assert_eq!(phase.next_bar_at(10_000), 0);
+    }
+
+    #[test]
+    fn catch_up_enters_on_the_first_reachable_bar() {
+        let phase = phase();
+        let catch_up = phase.catch_up(13_100, 300);
+        assert_eq!(catch_up.current_beat, 2.2);
+        assert_eq!(catch_up.server_time, 13_100);
+        assert_eq!(catch_up.entry_beat, 4);
+        assert_eq!(catch_up.entry_time, 14_000);
+
+        // Too close to bar 4 to make it, so bar 8
+        let catch_up = phase.catch_up(13_800, 300);
+        assert_eq!(catch_up.entry_beat, 8);
+        assert_eq!(catch_up.entry_time, 16_000);
+    }
+
+    /// Seat a client with one sample per RTT, connected unless `connected` is false
+    fn seat_with_rtts(state: &mut PerformanceState, seat: &str, rtts: &[i64], connected: bool) -> Option<ClientReceiver> {
+        let (sender, receiver) = outbox::channel(SlowClientPolicy::default());
+        let mut info = ClientInfo {
+            id: format!("client-{}", seat),
+            sender: connected.then_some(sender),
+            ..Default::default()
+        };
+        for rtt in rtts {
+            let sample = TimeSample {
+                client_send: NOW - rtt,
+                server_time: NOW - rtt / 2,
+                client_receive: NOW,
+            };
+            assert!(info.sync.add_sample(sample, NOW));
+        }
+        state.id_map.insert(info.id.clone(), seat.to_string());
+        state.seat_map.insert(seat.to_string(), info);
+        connected.then_some(receiver)
+    }
+
+    #[test]
+    fn lead_covers_the_worst_recent_rtt() {
+        let mut state = PerformanceState { safety_ms: 250, ..Default::default() };
+        assert_eq!(state.schedule_lead_ms(), 250);
+
+        // A jittery phone counts with its slow round trips, not its best one
+        let _jittery = seat_with_rtts(&mut state, "0", &[20, 200, 30], true);
+        let _steady = seat_with_rtts(&mut state, "1", &[50, 60], true);
+        // Disconnected phones don't hold anyone up
+        seat_with_rtts(&mut state, "2", &[900], false);
+        assert_eq!(state.worst_rtt_ms(), 200);
+        assert_eq!(state.schedule_lead_ms(), 450);
+    }
+
+    #[test]
+    fn lead_leaves_out_hopeless_rtts() {
+        let mut state = PerformanceState { safety_ms: 250, ..Default::default() };
+        let _near = seat_with_rtts(&mut state, "0", &[40], true);
+        let _stalled = seat_with_rtts(&mut state, "1", &[30, 8_000], true);
+        assert_eq!(state.worst_rtt_ms(), 40);
+
+        // Only the latest samples count, so a slow patch is forgotten
+        let recovered: Vec<i64> = std::iter::once(3_000).chain(std::iter::repeat_n(70, 8)).collect();
+        let _recovered = seat_with_rtts(&mut state, "2", &recovered, true);
+        assert_eq!(state.worst_rtt_ms(), 70);
+    }
+
+    #[test]
+    fn lead_does_not_overflow() {
+        let state = PerformanceState { safety_ms: u64::MAX, ..Default::default() };
+        assert_eq!(state.schedule_lead_ms(), i64::MAX);
+    }
+
+    /// Seats 0 and 1 taken, by a ready client and one still syncing
+    fn two_seated() -> PerformanceState {
+        let mut state = PerformanceState::default();
+        seat_with_rtts(&mut state, "0", &[], false);
+        seat_with_rtts(&mut state, "1", &[], false);
+        state.seat_map.get_mut("0").unwrap().status = ClientStatus::Ready;
+        state
+    }
+
+    fn seat_of<'a>(state: &'a PerformanceState, id: &str) -> Option<&'a str> {
+        state.id_map.get(id).map(String::as_str)
+    }
+
+    #[test]
+    fn swap_exchanges_seats_and_resets_readiness() {
+        let mut state = two_seated();
+        let mut moved = state.swap_seats("0", "1").unwrap();
+        moved.sort();
+        assert_eq!(moved, vec![("client-0".into(), "1".into()), ("client-1".into(), "0".into())]);
+        assert_eq!(seat_of(&state, "client-0"), Some("1"));
+        assert_eq!(seat_of(&state, "client-1"), Some("0"));
+        assert_eq!(state.seat_map["1"].id, "client-0");
+        // The new seat's files have to be checked again
+        assert_eq!(state.seat_map["1"].status, ClientStatus::Synced);
+        assert_eq!(state.seat_map["0"].status, ClientStatus::Joined);
+    }
+
+    #[test]
+    fn swap_with_an_empty_seat_moves() {
+        let mut state = two_seated();
+        assert_eq!(state.swap_seats("5", "0").unwrap(), vec![("client-0".into(), "5".into())]);
+        assert!(!state.seat_map.contains_key("0"));
+        assert_eq!(seat_of(&state, "client-0"), Some("5"));
+
+        assert!(matches!(state.swap_seats("2", "3"), Err(Error::SeatEmpty(_))));
+    }
+
+    #[test]
+    fn swapping_a_seat_with_itself_changes_nothing() {
+        let mut state = two_seated();
+        assert_eq!(state.swap_seats("0", "0").unwrap(), vec![]);
+        assert_eq!(state.seat_map["0"].status, ClientStatus::Ready);
+        assert_eq!(seat_of(&state, "client-0"), Some("0"));
+    }
+
+    #[test]
+    fn move_only_goes_to_empty_seats() {
+        let mut state = two_seated();
+        assert!(matches!(state.move_client("client-0", "1"), Err(Error::SeatTaken(_))));
+        assert!(matches!(state.move_client("nobody", "4"), Err(Error::ClientNotFound(_))));
+        assert_eq!(state.move_client("client-0", "0").unwrap(), "0");
+        assert_eq!(state.seat_map["0"].status, ClientStatus::Ready);
+
+        assert_eq!(state.move_client("client-0", "4").unwrap(), "0");
+        assert_eq!(seat_of(&state, "client-0"), Some("4"));
+        assert_eq!(state.seat_map.len(), 2);
+    }
+}
@@ -0,0 +1,231 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use warp::ws::Message;
+
+use crate::state::{PhaseCatchUpPayload, PhaseStartPayload, PhaseStopPayload};
+
+/// Bumped whenever a message changes shape. Clients send it on join and rejoin.
+pub const PROTOCOL_VERSION: u32 = 6;
+
+/// Seat numbers arrive either as text or as a JSON number
+#[derive(Debug, Clone, Deserialize, TS, JsonSchema)]
+#[serde(untagged)]
+pub enum SeatValue {
+    Text(String),
+    Number(#[ts(type = "number")] u64),
+}
+
+impl SeatValue {
+    pub fn into_string(self) -> String {
+        match self {
+            SeatValue::Text(s) => s.trim().to_string(),
+            SeatValue::Number(n) => n.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileType {
+    Patch,
+    Sheet,
+}
+
+//
+// Client → Server
+//
+
+#[derive(Debug, Deserialize, TS, JsonSchema)]
+#[serde(tag = "type")]
+pub enum ClientMessage {
+    #[serde(rename = "ping")]
+    Ping,
+    #[serde(rename = "j")]
+    Join {
+        seat: SeatValue,
+        #[ts(optional)]
+        protocol: Option<u32>,
+        /// Session join code, if the server asks for one
+        #[ts(optional)]
+        code: Option<String>,
+        /// Seat ticket from a seat's QR code
+        #[ts(optional)]
+        token: Option<String>,
+    },
+    #[serde(rename = "rj")]
+    Rejoin {
+        id: String,
+        #[ts(optional)]
+        protocol: Option<u32>,
+    },
+    #[serde(rename = "refresh")]
+    Refresh { id: String },
+    #[serde(rename = "time_request")]
+    TimeRequest {
+        #[ts(type = "number")]
+        client_time: i64,
+    },
+    #[serde(rename = "time_sample")]
+    TimeSample {
+        #[ts(type = "number")]
+        client_time: i64,
+        #[ts(type = "number")]
+        server_time: i64,
+        #[ts(type = "number")]
+        receive_time: i64,
+    },
+    #[serde(rename = "ready")]
+    Ready { id: String },
+    #[serde(rename = "file_request")]
+    FileRequest {
+        #[serde(alias = "id")]
+        name: String,
+        #[serde(rename = "fileType")]
+        file_type: FileType,
+    },
+    #[serde(rename = "loaded")]
+    Loaded { id: String },
+    #[serde(other)]
+    #[ts(skip)]
+    #[schemars(skip)]
+    Unknown,
+}
+
+//
+// Server → Client
+//
+
+#[derive(Debug, Clone, Copy, Serialize, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidJson,
+    UnknownMessage,
+    InvalidMessage,
+    ProtocolMismatch,
+    InvalidSeat,
+    SeatTaken,
+    NoFreeSeat,
+    JoinCodeRequired,
+    JoinCodeInvalid,
+    SeatTokenRequired,
+    SeatTokenInvalid,
+    SessionNotFound,
+    ClientIdInvalid,
+    FileNotFound,
+    SlowConnection,
+}
+
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+pub struct ManifestEntry {
+    pub name: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub hash: String,
+}
+
+#[derive(Debug, Serialize, TS, JsonSchema)]
+#[serde(tag = "type")]
+pub enum ServerMessage {
+    #[serde(rename = "pong")]
+    Pong,
+    #[serde(rename = "joined")]
+    Joined {
+        id: String,
+        /// Seat index, the key used in phase assignments
+        seat: String,
+        /// Row letter, A for the first row
+        row: String,
+        /// Seat number within the row, from 1
+        column: usize,
+        #[serde(rename = "expiresAt")]
+        #[ts(type = "number")]
+        expires_at: u64,
+        protocol: u32,
+    },
+    #[serde(rename = "refreshed")]
+    Refreshed {
+        #[serde(rename = "expiresAt")]
+        #[ts(type = "number")]
+        expires_at: u64,
+    },
+    #[serde(rename = "tq_result")]
+    TimeResult {
+        #[ts(type = "number")]
+        client_time: i64,
+        #[ts(type = "number")]
+        server_time: i64,
+    },
+    #[serde(rename = "offset_update")]
+    OffsetUpdate {
+        offset_ms: Option<f64>,
+        skew_ppm: Option<f64>,
+        #[ts(type = "number")]
+        server_time: i64,
+    },
+    /// The admin moved this client to another seat. It should send `ready` for the new seat's files.
+    #[serde(rename = "seat_changed")]
+    SeatChanged {
+        seat: String,
+        row: String,
+        column: usize,
+    },
+    /// The admin removed this client from its seat; its ID is no longer valid
+    #[serde(rename = "kicked")]
+    Kicked { message: String },
+    #[serde(rename = "file_manifest")]
+    FileManifest {
+        seat: String,
+        patch_files: Vec<ManifestEntry>,
+        sheet_files: Vec<ManifestEntry>,
+    },
+    /// JSON header at the front of each binary file chunk
+    #[serde(rename = "file_chunk")]
+    FileChunk {
+        id: String,
+        #[serde(rename = "fileType")]
+        file_type: FileType,
+        #[serde(rename = "isLast")]
+        is_last: bool,
+    },
+    #[serde(rename = "phase_start")]
+    PhaseStart(PhaseStartPayload),
+    /// Sent in reply to `loaded` while a phase is playing, so late joiners come in at the next bar
+    #[serde(rename = "phase_catch_up")]
+    PhaseCatchUp(PhaseCatchUpPayload),
+    #[serde(rename = "phase_stop")]
+    PhaseStop(PhaseStopPayload),
+    #[serde(rename = "error")]
+    Error { code: ErrorCode, message: String },
+}
+
+impl ServerMessage {
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        ServerMessage::Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Reply for clients whose protocol version is missing or different from ours
+    pub fn check_protocol(protocol: Option<u32>) -> Option<Self> {
+        if protocol == Some(PROTOCOL_VERSION) {
+            return None;
+        }
+        Some(ServerMessage::error(
+            ErrorCode::ProtocolMismatch,
+            "This page is out of date. Please reload it.",
+        ))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("server messages always serialize")
+    }
+}
+
+impl From<ServerMessage> for Message {
+    fn from(msg: ServerMessage) -> Self {
+        Message::text(msg.to_json())
+    }
+}
+
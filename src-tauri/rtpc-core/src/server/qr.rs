@@ -0,0 +1,228 @@
+use base64::Engine;
+use qrcode::render::svg;
+use qrcode::{Color, EcLevel, QrCode};
+use schemars::JsonSchema;
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::seats::{self, Seat};
+use crate::error::{Error, Result};
+use crate::state::SessionConfig;
+
+/// Blank modules around the code, as the QR spec asks for
+const QUIET_ZONE: usize = 4;
+
+/// Pixels per module in PNG output
+const PNG_SCALE: usize = 8;
+
+/// A join link and its QR code, ready to show or save
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+pub struct JoinQr {
+    pub url: String,
+    pub svg: String,
+    /// PNG, base64-encoded
+    pub png: String,
+}
+
+/// Where clients reach the server: HTTPS on the TLS port when there is one, else plain HTTP
+pub fn base_url(host: &str, tls_port: Option<u16>, plain_port: Option<u16>) -> String {
+    match (tls_port, plain_port) {
+        (Some(port), _) => format!("https://{}:{}", host, port),
+        (None, Some(port)) => format!("http://{}:{}", host, port),
+        (None, None) => format!("https://{}", host),
+    }
+}
+
+/// The canonical join link: the server's base URL with the seat (as `C4`), join code and seat
+/// token as query parameters, which the client's login page reads.
+pub fn join_url(base: &str, seat: Option<&Seat>, code: Option<&str>, token: Option<&str>) -> String {
+    let mut params = Vec::new();
+    if let Some(seat) = seat {
+        params.push(format!("seat={}", encode_query_value(&seat.label())));
+    }
+    if let Some(code) = code.filter(|code| !code.is_empty()) {
+        params.push(format!("code={}", encode_query_value(code)));
+    }
+    if let Some(token) = token.filter(|token| !token.is_empty()) {
+        params.push(format!("token={}", encode_query_value(token)));
+    }
+
+    let base = base.trim_end_matches('/');
+    if params.is_empty() {
+        format!("{}/", base)
+    } else {
+        format!("{}/?{}", base, params.join("&"))
+    }
+}
+
+pub fn join_qr(url: String) -> Result<JoinQr> {
+    let code = encode(&url)?;
+    let svg = render_svg(&code);
+    let png = base64::engine::general_purpose::STANDARD.encode(render_png(&code)?);
+    Ok(JoinQr { url, svg, png })
+}
+
+/// A printable HTML page with one QR code per seat, labelled with its row and seat number, to
+/// hand out or tape to chairs. `seat_token` gives each seat's ticket.
+pub fn seat_sheet(
+    base: &str,
+    config: &SessionConfig,
+    seat_token: impl Fn(&Seat) -> Option<String>,
+) -> Result<String> {
+    let mut cards = String::new();
+    for seat in seats::all(config) {
+        let url = join_url(base, Some(&seat), None, seat_token(&seat).as_deref());
+        let svg = render_svg(&encode(&url)?);
+        cards.push_str(&format!(
+            r#"<div class="card">{}<div class="seat">{}</div><div class="place">Row {}, Seat {}</div></div>
+"#,
+            svg,
+            seat.label(),
+            seat.row_label(),
+            seat.number(),
+        ));
+    }
+
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title} – seats</title>
+<style>
+  body {{ font-family: sans-serif; margin: 0; }}
+  h1 {{ font-size: 14pt; margin: 12mm 12mm 0; }}
+  .grid {{ display: grid; grid-template-columns: repeat(3, 1fr); gap: 8mm; padding: 12mm; }}
+  .card {{ border: 1px dashed #999; padding: 4mm; text-align: center; break-inside: avoid; }}
+  .card svg {{ width: 100%; height: auto; }}
+  .seat {{ font-size: 20pt; font-weight: bold; }}
+  .place {{ font-size: 10pt; color: #555; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<div class="grid">
+{cards}</div>
+</body>
+</html>
+"#,
+        title = escape_html(&config.name),
+        cards = cards,
+    ))
+}
+
+fn encode(data: &str) -> Result<QrCode> {
+    QrCode::with_error_correction_level(data, EcLevel::M).map_err(|e| Error::Qr(e.to_string()))
+}
+
+fn render_svg(code: &QrCode) -> String {
+    let svg = code
+        .render::<svg::Color>()
+        .min_dimensions(256, 256)
+        .quiet_zone(true)
+        .build();
+    // Drop the XML declaration so the SVG can be inlined in HTML
+    match svg.find("<svg") {
+        Some(start) => svg[start..].to_string(),
+        None => svg,
+    }
+}
+
+fn render_png(code: &QrCode) -> Result<Vec<u8>> {
+    let modules = code.width();
+    let size = (modules + 2 * QUIET_ZONE) * PNG_SCALE;
+    let colors = code.to_colors();
+
+    // 8-bit grayscale, white by default
+    let mut pixels = vec![255u8; size * size];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let x0 = (i % modules + QUIET_ZONE) * PNG_SCALE;
+        let y0 = (i / modules + QUIET_ZONE) * PNG_SCALE;
+        for y in y0..y0 + PNG_SCALE {
+            pixels[y * size + x0..y * size + x0 + PNG_SCALE].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    let png_err = |e: png::EncodingError| Error::Qr(e.to_string());
+    let mut encoder = png::Encoder::new(&mut out, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(png_err)?;
+    writer.write_image_data(&pixels).map_err(png_err)?;
+    writer.finish().map_err(png_err)?;
+    Ok(out)
+}
+
+/// Percent-encode everything but unreserved characters
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str) -> SessionConfig {
+        SessionConfig { name: name.into(), path: "test.json".into(), rows: 2, columns: 3 }
+    }
+
+    #[test]
+    fn prefers_https_when_tls_is_served() {
+        assert_eq!(base_url("192.0.2.10", Some(8443), Some(8080)), "https://192.0.2.10:8443");
+        assert_eq!(base_url("rtpc.local", None, Some(8080)), "http://rtpc.local:8080");
+        assert_eq!(base_url("[fe80::1]", Some(443), None), "https://[fe80::1]:443");
+    }
+
+    #[test]
+    fn puts_seat_code_and_token_in_the_query() {
+        let seat = seats::all(&config("test")).nth(4).unwrap();
+        assert_eq!(join_url("http://rtpc.local:8080/", None, None, None), "http://rtpc.local:8080/");
+        assert_eq!(join_url("http://rtpc.local:8080", None, Some(""), Some("")), "http://rtpc.local:8080/");
+        assert_eq!(
+            join_url("https://192.0.2.10:8443", Some(&seat), Some("4711"), Some("4.abc_-")),
+            "https://192.0.2.10:8443/?seat=B2&code=4711&token=4.abc_-"
+        );
+    }
+
+    #[test]
+    fn escapes_codes_and_tokens() {
+        let url = join_url("https://h", None, Some("a b&c=d"), Some("x/y+z?"));
+        assert_eq!(url, "https://h/?code=a%20b%26c%3Dd&token=x%2Fy%2Bz%3F");
+        assert_eq!(encode_query_value("ü"), "%C3%BC");
+    }
+
+    #[test]
+    fn the_seat_sheet_labels_every_seat() {
+        let config = config("Tom & Jerry <live>");
+        let sheet = seat_sheet("https://h", &config, |seat| Some(format!("t{}", seat.index))).unwrap();
+        assert!(sheet.contains("<title>Tom &amp; Jerry &lt;live&gt; – seats</title>"));
+        assert!(!sheet.contains("<live>"));
+        assert_eq!(sheet.matches(r#"<div class="card">"#).count(), 6);
+        assert!(sheet.contains(r#"<div class="seat">B3</div><div class="place">Row B, Seat 3</div>"#));
+    }
+
+    #[test]
+    fn encodes_a_scannable_png() {
+        let qr = join_qr("https://192.0.2.10:8443/?seat=A1".into()).unwrap();
+        let png = base64::engine::general_purpose::STANDARD.decode(qr.png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert!(qr.svg.starts_with("<svg"));
+    }
+}
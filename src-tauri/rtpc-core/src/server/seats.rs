@@ -0,0 +1,296 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::str::FromStr;
+use ts_rs::TS;
+
+use crate::error::{Error, Result};
+use crate::state::SessionConfig;
+
+/// What a client sends as its seat to have the server pick one
+pub const ANY_SEAT: &str = "any";
+
+/// A seat in the session grid. Seats are numbered row by row from 0; that index is the seat's
+/// key on the wire and in phase assignments. People see the row letter and the seat in the row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seat {
+    pub index: usize,
+    /// From 0, front row first
+    pub row: usize,
+    /// From 0
+    pub column: usize,
+}
+
+impl Seat {
+    /// The seat's key in the seat map and in phase assignments
+    pub fn key(&self) -> String {
+        self.index.to_string()
+    }
+
+    /// Row letter: A, B, … Z, AA, AB, …
+    pub fn row_label(&self) -> String {
+        row_label(self.row)
+    }
+
+    /// Seat number within the row, from 1
+    pub fn number(&self) -> usize {
+        self.column + 1
+    }
+
+    /// Short form, e.g. `C4`
+    pub fn label(&self) -> String {
+        format!("{}{}", self.row_label(), self.number())
+    }
+
+    /// Squared distance on the grid, in seats
+    fn distance2(&self, other: &Seat) -> usize {
+        self.row.abs_diff(other.row).pow(2) + self.column.abs_diff(other.column).pow(2)
+    }
+}
+
+/// Every seat of the session, in index order
+pub fn all(config: &SessionConfig) -> impl Iterator<Item = Seat> + '_ {
+    (0..config.rows * config.columns).map(|index| Seat {
+        index,
+        row: index / config.columns,
+        column: index % config.columns,
+    })
+}
+
+/// Find a seat given as its index (`14`) or as row letter and seat number (`C4`, `c 4`, `C-4`)
+pub fn resolve(config: &SessionConfig, text: &str) -> Result<Seat> {
+    let invalid = || Error::InvalidSeat {
+        seat: text.to_string(),
+        rows: config.rows,
+        columns: config.columns,
+    };
+    let text = text.trim();
+
+    if !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) {
+        let index: usize = text.parse().map_err(|_| invalid())?;
+        return all(config).nth(index).ok_or_else(invalid);
+    }
+
+    let letters = text.bytes().take_while(u8::is_ascii_alphabetic).count();
+    let (row, rest) = text.split_at(letters);
+    let number = rest.trim_start_matches([' ', '-', ':']);
+    if row.is_empty() || number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let row = parse_row_label(row).ok_or_else(invalid)?;
+    let column = number.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).ok_or_else(invalid)?;
+    if row >= config.rows || column >= config.columns {
+        return Err(invalid());
+    }
+    Ok(Seat {
+        index: row * config.columns + column,
+        row,
+        column,
+    })
+}
+
+/// 0 → A, 25 → Z, 26 → AA, as on spreadsheet columns
+pub fn row_label(row: usize) -> String {
+    let mut label = Vec::new();
+    let mut n = row + 1;
+    while n > 0 {
+        n -= 1;
+        label.push(b'A' + (n % 26) as u8);
+        n /= 26;
+    }
+    label.reverse();
+    String::from_utf8(label).expect("row labels are ASCII")
+}
+
+fn parse_row_label(label: &str) -> Option<usize> {
+    label
+        .bytes()
+        .try_fold(0usize, |n, b| n.checked_mul(26)?.checked_add((b.to_ascii_uppercase() - b'A') as usize + 1))
+        .and_then(|n| n.checked_sub(1))
+}
+
+/// How the server picks a seat for clients that join with `any`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SeatAllocation {
+    /// The first free seat, row by row
+    #[default]
+    InOrder,
+    /// The free seat farthest from everyone already seated
+    Spread,
+    /// Any free seat, from a seeded sequence so a run can be repeated
+    Random,
+    /// Free seats with something assigned in the current phase first, then in order
+    Assigned,
+}
+
+impl FromStr for SeatAllocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "in-order" | "in_order" => Ok(SeatAllocation::InOrder),
+            "spread" => Ok(SeatAllocation::Spread),
+            "random" => Ok(SeatAllocation::Random),
+            "assigned" => Ok(SeatAllocation::Assigned),
+            _ => Err(format!("Unknown seat allocation `{}` (in-order, spread, random or assigned)", s)),
+        }
+    }
+}
+
+/// Picks seats for `any` joins, keeping the random sequence between picks
+#[derive(Debug, Clone, Default)]
+pub struct SeatAllocator {
+    pub strategy: SeatAllocation,
+    rng: u64,
+}
+
+impl SeatAllocator {
+    pub fn new(strategy: SeatAllocation, seed: u64) -> Self {
+        SeatAllocator { strategy, rng: seed }
+    }
+
+    /// A free seat, or `None` if the session is full. `preferred` holds the indexes of seats
+    /// with assignments in the current phase, for [`SeatAllocation::Assigned`].
+    pub fn pick(
+        &mut self,
+        config: &SessionConfig,
+        taken: impl Fn(&Seat) -> bool,
+        preferred: &HashSet<usize>,
+    ) -> Option<Seat> {
+        let (seated, free): (Vec<Seat>, Vec<Seat>) = all(config).partition(|seat| taken(seat));
+
+        match self.strategy {
+            SeatAllocation::InOrder => free.first().copied(),
+            SeatAllocation::Spread => {
+                // Ties go to the lower index, so the first client gets A1 and the next the far corner
+                let distance = |seat: &Seat| seated.iter().map(|other| seat.distance2(other)).min();
+                free.iter()
+                    .copied()
+                    .reduce(|best, seat| if distance(&seat) > distance(&best) { seat } else { best })
+            }
+            SeatAllocation::Random => {
+                if free.is_empty() {
+                    return None;
+                }
+                let n = self.next_random() % free.len() as u64;
+                Some(free[n as usize])
+            }
+            SeatAllocation::Assigned => free
+                .iter()
+                .find(|seat| preferred.contains(&seat.index))
+                .or(free.first())
+                .copied(),
+        }
+    }
+
+    /// splitmix64: tiny, and gives the same sequence for a seed on every platform
+    fn next_random(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three rows of five
+    fn config() -> SessionConfig {
+        SessionConfig { name: "test".into(), path: String::new(), rows: 3, columns: 5 }
+    }
+
+    /// Seat `count` clients one after another, returning the picked indexes
+    fn fill(
+        allocator: &mut SeatAllocator,
+        taken: &mut HashSet<usize>,
+        preferred: &HashSet<usize>,
+        count: usize,
+    ) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                let seat = allocator
+                    .pick(&config(), |seat| taken.contains(&seat.index), preferred)
+                    .expect("a free seat");
+                assert!(taken.insert(seat.index), "seat {} picked twice", seat.index);
+                seat.index
+            })
+            .collect()
+    }
+
+    fn order(strategy: SeatAllocation, seed: u64) -> Vec<usize> {
+        fill(&mut SeatAllocator::new(strategy, seed), &mut HashSet::new(), &HashSet::new(), 15)
+    }
+
+    #[test]
+    fn in_order_fills_row_by_row() {
+        assert_eq!(order(SeatAllocation::InOrder, 0), (0..15).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn random_repeats_for_a_seed() {
+        let first = order(SeatAllocation::Random, 42);
+        assert_eq!(order(SeatAllocation::Random, 42), first);
+        assert_ne!(order(SeatAllocation::Random, 43), first);
+
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..15).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn spread_keeps_away_from_everyone_seated() {
+        let mut allocator = SeatAllocator::new(SeatAllocation::Spread, 0);
+        let mut taken = HashSet::new();
+        // A1, then the far corner
+        assert_eq!(fill(&mut allocator, &mut taken, &HashSet::new(), 2), vec![0, 14]);
+
+        while taken.len() < 15 {
+            let nearest = |seat: &Seat| {
+                all(&config())
+                    .filter(|other| taken.contains(&other.index))
+                    .map(|other| seat.distance2(&other))
+                    .min()
+            };
+            let farthest = all(&config())
+                .filter(|seat| !taken.contains(&seat.index))
+                .filter_map(|seat| nearest(&seat))
+                .max();
+            let picked = allocator
+                .pick(&config(), |seat| taken.contains(&seat.index), &HashSet::new())
+                .expect("a free seat");
+            assert_eq!(nearest(&picked), farthest, "seat {} isn't the farthest", picked.label());
+            taken.insert(picked.index);
+        }
+    }
+
+    #[test]
+    fn assigned_prefers_seats_with_parts() {
+        let preferred = HashSet::from([4, 9]);
+        let picked = fill(
+            &mut SeatAllocator::new(SeatAllocation::Assigned, 0),
+            &mut HashSet::from([0]),
+            &preferred,
+            4,
+        );
+        assert_eq!(picked, vec![4, 9, 1, 2]);
+    }
+
+    #[test]
+    fn a_full_session_has_no_seat() {
+        let strategies = [
+            SeatAllocation::InOrder,
+            SeatAllocation::Spread,
+            SeatAllocation::Random,
+            SeatAllocation::Assigned,
+        ];
+        for strategy in strategies {
+            let mut allocator = SeatAllocator::new(strategy, 7);
+            assert_eq!(allocator.pick(&config(), |_| true, &HashSet::from([3])), None, "{:?}", strategy);
+        }
+    }
+}
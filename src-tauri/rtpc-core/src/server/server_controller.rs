@@ -0,0 +1,798 @@
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
+use ts_rs::TS;
+use warp::filters::path::FullPath;
+use warp::http::{StatusCode, Uri};
+use warp::Reply;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use std::{collections::HashMap, net::SocketAddr};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use warp::Filter;
+
+use crate::access::JoinAccess;
+use crate::clock::{MonotonicClock, SharedClock};
+use crate::error::Error;
+use crate::events::SharedEvents;
+use crate::handlers::{broadcast_to_all, emit_status, handle_message};
+use crate::protocol::{ErrorCode, ServerMessage};
+use crate::performance_types::{ActivePhase, PerformanceState};
+use crate::tls::{TlsInfo, TlsSource};
+use crate::mdns::{local_hostname, MdnsAdvertiser, MdnsService};
+use crate::network::url_host;
+use crate::journal::Journal;
+use crate::outbox::{self, SlowClientPolicy};
+use crate::qr::{self, JoinQr};
+use crate::seats::{self, Seat, SeatAllocator};
+use crate::{Listen, ServerOptions};
+use crate::state::{AppState, AssignmentPayload, PhaseStopPayload};
+
+/// How often expired client leases are swept
+const REAPER_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the broker journal is checked for changes and rewritten
+const JOURNAL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Controls the WebSocket and static file servers for real-time performances
+pub struct ServerController {
+    pub servers: Vec<JoinHandle<()>>,
+    pub reaper: Option<JoinHandle<()>>,
+    pub resync: Option<JoinHandle<()>>,
+    pub resync_interval: Duration,
+    pub perf_state: Arc<Mutex<PerformanceState>>,
+    pub app_state: Arc<AppState>,
+    pub events: SharedEvents,
+    pub clock: SharedClock,
+    pub info: Option<ServerInfo>,
+    pub started_at: Option<Instant>,
+    pub mdns: Option<MdnsAdvertiser>,
+    pub journal: Option<JoinHandle<()>>,
+    /// Set on stop, ending the listeners and closing every client socket
+    shutdown: watch::Sender<bool>,
+    /// Where seats and the playing phase are journaled, next to the session file
+    pub journal_path: Option<PathBuf>,
+}
+
+impl ServerController {
+    /// Create a new controller with a session TTL and scheduling safety window in milliseconds,
+    /// pushing corrected clock offsets to clients every `resync_interval`
+    pub fn new(
+        ttl_ms: u64,
+        safety_ms: u64,
+        resync_interval: Duration,
+        app_state: Arc<AppState>,
+        events: SharedEvents,
+    ) -> Self {
+        let state = PerformanceState {
+            session_ttl_ms: ttl_ms,
+            safety_ms,
+            ..Default::default()
+        };
+        ServerController {
+            servers: Vec::new(),
+            reaper: None,
+            resync: None,
+            resync_interval,
+            perf_state: Arc::new(Mutex::new(state)),
+            app_state,
+            events,
+            clock: Arc::new(MonotonicClock::new()),
+            info: None,
+            started_at: None,
+            mdns: None,
+            journal: None,
+            journal_path: None,
+            shutdown: watch::channel(false).0,
+        }
+    }
+
+    /// Start the listeners: WSS and/or plain WS with the static client on each, plus an optional
+    /// HTTP → HTTPS redirect. Port 0 picks a free port. All ports are bound before anything is
+    /// served, so a port in use fails the whole start. Once running, the server is advertised
+    /// over mDNS if enabled. Returns what is being served.
+    pub async fn start(&mut self, listen: &Listen, tls: &TlsSource) -> crate::Result<ServerInfo> {
+        if self.is_running() {
+            return Err(Error::AlreadyRunning);
+        }
+
+        let identity = match listen.tls_port {
+            Some(_) => {
+                let hostname = listen.mdns_hostname.as_deref().map(local_hostname);
+                Some(tls.load(Some(listen.advertise_ip), hostname.as_deref())?)
+            }
+            None => None,
+        };
+        let tls_info = identity.as_ref().map(|identity| identity.info.clone());
+        if self.perf_state.lock().await.access.require_seat_token {
+            self.ensure_seat_secret().await;
+        }
+        self.shutdown.send_replace(false);
+        let mut servers: Vec<BoxFuture<'static, ()>> = Vec::new();
+
+        let mut tls_port = None;
+        if let (Some(port), Some(identity)) = (listen.tls_port, identity) {
+            let bound_port = Arc::new(OnceLock::new());
+            let (addr, server) = warp::serve(self.routes(bound_port.clone()))
+                .tls()
+                .cert(identity.cert_pem)
+                .key(identity.key_pem)
+                .try_bind_with_graceful_shutdown(SocketAddr::new(listen.bind_ip, port), self.shutdown_signal())
+                .map_err(|e| Error::bind(port, e))?;
+            bound_port.set(addr.port()).ok();
+            tls_port = Some(addr.port());
+            servers.push(server.boxed());
+        }
+
+        let mut plain_port = None;
+        if let Some(port) = listen.plain_port {
+            let bound_port = Arc::new(OnceLock::new());
+            let (addr, server) = warp::serve(self.routes(bound_port.clone()))
+                .try_bind_with_graceful_shutdown(SocketAddr::new(listen.bind_ip, port), self.shutdown_signal())
+                .map_err(|e| Error::bind(port, e))?;
+            bound_port.set(addr.port()).ok();
+            plain_port = Some(addr.port());
+            servers.push(server.boxed());
+        }
+
+        let mut redirect_port = None;
+        if let (Some(port), Some(tls_port)) = (listen.redirect_port, tls_port) {
+            let (addr, server) = warp::serve(redirect_to_https(tls_port))
+                .try_bind_with_graceful_shutdown(SocketAddr::new(listen.bind_ip, port), self.shutdown_signal())
+                .map_err(|e| Error::bind(port, e))?;
+            redirect_port = Some(addr.port());
+            servers.push(server.boxed());
+        }
+
+        // Seats from before a restart, so their clients can rejoin
+        if let Some(path) = &self.journal_path {
+            self.restore_journal(path).await;
+        }
+
+        // Everything is bound, start serving
+        self.servers = servers.into_iter().map(tokio::spawn).collect();
+        if let (Some(port), Some(tls_info)) = (tls_port, &tls_info) {
+            println!("🟢 WSS/HTTPS listening on wss://{}:{}", url_host(listen.bind_ip), port);
+            println!("[tls] certificate {} (SHA-256 {})", tls_info.cert_path, tls_info.fingerprint);
+        }
+        if let Some(port) = plain_port {
+            println!("🟢 WS/HTTP listening on ws://{}:{}", url_host(listen.bind_ip), port);
+        }
+        if let Some(port) = redirect_port {
+            println!("🟢 HTTP on port {} redirects to HTTPS", port);
+        }
+
+        self.reaper = Some(self.spawn_reaper());
+        self.resync = Some(self.spawn_resync());
+        self.journal = self.journal_path.clone().map(|path| self.spawn_journal(path));
+        self.started_at = Some(Instant::now());
+
+        // Advertising is a convenience, so a network that blocks multicast doesn't stop the show
+        let hostname = listen.mdns_hostname.as_deref().map(local_hostname);
+        if let (Some(label), Some(port)) = (&listen.mdns_hostname, tls_port.or(plain_port)) {
+            let instance = match self.app_state.session_config().await {
+                Some(config) => config.name,
+                None => "RTPC".to_string(),
+            };
+            let service = MdnsService {
+                hostname: label,
+                instance: &instance,
+                ip: listen.advertise_ip,
+                port,
+                tls: tls_port.is_some(),
+            };
+            match MdnsAdvertiser::advertise(&service) {
+                Ok(mdns) => self.mdns = Some(mdns),
+                Err(e) => eprintln!("[mdns] {}", e),
+            }
+        }
+
+        let access = self.perf_state.lock().await.access.clone();
+        let host = match &hostname {
+            Some(hostname) if listen.join_by_hostname && self.mdns.is_some() => hostname.clone(),
+            _ => url_host(listen.advertise_ip),
+        };
+        let join_url = qr::base_url(&host, tls_port, plain_port);
+        let info = ServerInfo {
+            address: listen.advertise_ip.to_string(),
+            join_url,
+            hostname: hostname.filter(|_| self.mdns.is_some()),
+            bind_address: listen.bind_ip.to_string(),
+            tls_port,
+            plain_port,
+            redirect_port,
+            tls: tls_info,
+            join_code: access.code,
+            require_seat_tokens: access.require_seat_token,
+        };
+        self.info = Some(info.clone());
+        Ok(info)
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.servers.is_empty()
+    }
+
+    /// Running state, listening ports, uptime and connected client count
+    pub async fn status(&self) -> ServerStatus {
+        let client_count = self
+            .perf_state
+            .lock()
+            .await
+            .client_summaries()
+            .iter()
+            .filter(|client| client.connected)
+            .count();
+
+        ServerStatus {
+            running: self.is_running(),
+            address: self.info.as_ref().map(|info| info.address.clone()),
+            info: self.info.clone(),
+            uptime_ms: self.started_at.map_or(0, |t| t.elapsed().as_millis() as u64),
+            client_count,
+        }
+    }
+
+    /// Set the join code and seat ticket rules for new joins
+    pub async fn set_join_access(&self, access: JoinAccess) {
+        self.perf_state.lock().await.access = access;
+    }
+
+    /// Set what happens to clients that fall too far behind on their messages
+    pub async fn set_slow_client_policy(&self, policy: SlowClientPolicy) {
+        self.perf_state.lock().await.slow_clients = policy;
+    }
+
+    /// Use `clock` for every timestamp instead of the monotonic wall clock, e.g. a
+    /// [`FixedClock`](crate::clock::FixedClock) in tests
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Journal seats and the playing phase to this file, restoring them on start. Use
+    /// [`journal::path_for`] with the session file that was actually loaded.
+    pub fn set_journal_path(&mut self, path: Option<PathBuf>) {
+        self.journal_path = path;
+    }
+
+    /// Set how seats are picked for clients that join with `any`
+    pub async fn set_seat_allocator(&self, allocator: SeatAllocator) {
+        self.perf_state.lock().await.allocator = allocator;
+    }
+
+    /// QR code for the join link. With a seat (its index or a label like `C4`), the link carries
+    /// that seat's ticket; `include_code` also puts the join code in the link.
+    pub async fn join_qr(&self, seat: Option<&str>, include_code: bool) -> crate::Result<JoinQr> {
+        let info = self.info.as_ref().ok_or(Error::ServerNotRunning)?;
+        let seat = match seat {
+            Some(seat) => Some(self.resolve_seat(seat).await?),
+            None => None,
+        };
+        if seat.is_some() {
+            self.ensure_seat_secret().await;
+        }
+        let access = self.perf_state.lock().await.access.clone();
+        let code = access.code.as_deref().filter(|_| include_code);
+        let token = seat.and_then(|seat| access.seat_token(&seat.key()));
+        qr::join_qr(qr::join_url(&info.join_url, seat.as_ref(), code, token.as_deref()))
+    }
+
+    /// Write a printable HTML sheet with a ticket QR code for every seat in the session
+    pub async fn export_seat_sheet(&self, path: &Path) -> crate::Result<()> {
+        let info = self.info.as_ref().ok_or(Error::ServerNotRunning)?;
+        let config = self.app_state.session_config().await.ok_or(Error::NoSession)?;
+        self.ensure_seat_secret().await;
+        let access = self.perf_state.lock().await.access.clone();
+        let html = qr::seat_sheet(&info.join_url, &config, |seat| access.seat_token(&seat.key()))?;
+        std::fs::write(path, html)
+            .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))?;
+
+        println!("[qr] seat sheet for {} seats written to {}", config.rows * config.columns, path.display());
+        Ok(())
+    }
+
+    /// Seat tickets are about to be issued or required: sign them with the session's seat
+    /// secret, creating it if this is the first time
+    async fn ensure_seat_secret(&self) {
+        if self.perf_state.lock().await.access.has_secret() {
+            return;
+        }
+        let secret = self.app_state.issue_seat_secret().await;
+        self.perf_state.lock().await.access.set_secret(&secret);
+    }
+
+    /// The client in a seat, given as its index or a label like `C4`
+    pub async fn client_at(&self, seat: &str) -> crate::Result<String> {
+        let seat = self.resolve_seat(seat).await?;
+        self.perf_state
+            .lock()
+            .await
+            .seat_map
+            .get(&seat.key())
+            .map(|info| info.id.clone())
+            .ok_or_else(|| Error::SeatEmpty(seat.label()))
+    }
+
+    /// Remove a client from its seat and tell it so, freeing the seat for someone else
+    pub async fn kick_client(&self, client_id: &str) -> crate::Result<()> {
+        let (seat, info) = {
+            let mut perf = self.perf_state.lock().await;
+            let seat = perf
+                .id_map
+                .get(client_id)
+                .cloned()
+                .ok_or_else(|| Error::ClientNotFound(client_id.to_string()))?;
+            let info = perf.remove_seat(&seat).ok_or_else(|| Error::ClientNotFound(client_id.to_string()))?;
+            (seat, info)
+        };
+
+        if let Some(sender) = info.sender {
+            let msg = ServerMessage::Kicked { message: "You were removed from your seat".into() };
+            let _ = sender.send(msg);
+        }
+        println!("[admin] kicked client {} from seat {}", client_id, seat);
+        self.events.emit("client-kicked", json!({ "seat": seat, "id": client_id }));
+        Ok(())
+    }
+
+    /// Move a client to an empty seat. It is told its new seat and fetches that seat's files.
+    pub async fn move_client(&self, client_id: &str, seat: &str) -> crate::Result<()> {
+        let to = self.resolve_seat(seat).await?;
+        let from = self.perf_state.lock().await.move_client(client_id, &to.key())?;
+        if from == to.key() {
+            return Ok(());
+        }
+        println!("[admin] moved client {} from seat {} to {}", client_id, from, to.key());
+        self.notify_seat_changed(client_id, &to).await;
+        Ok(())
+    }
+
+    /// Exchange the clients in two seats, or move one into the other if it is empty
+    pub async fn swap_seats(&self, a: &str, b: &str) -> crate::Result<()> {
+        let (a, b) = (self.resolve_seat(a).await?, self.resolve_seat(b).await?);
+        if a == b {
+            return Ok(());
+        }
+        let moved = self.perf_state.lock().await.swap_seats(&a.key(), &b.key())?;
+        println!("[admin] swapped seats {} and {}", a.key(), b.key());
+        for (client_id, seat) in moved {
+            let place = if seat == a.key() { a } else { b };
+            self.notify_seat_changed(&client_id, &place).await;
+        }
+        Ok(())
+    }
+
+    async fn notify_seat_changed(&self, client_id: &str, seat: &Seat) {
+        let Some((sender, status)) = self
+            .perf_state
+            .lock()
+            .await
+            .seat_map
+            .get(&seat.key())
+            .map(|info| (info.sender.clone(), info.status))
+        else {
+            return;
+        };
+        if let Some(sender) = sender {
+            let msg = ServerMessage::SeatChanged {
+                seat: seat.key(),
+                row: seat.row_label(),
+                column: seat.number(),
+            };
+            let _ = sender.send(msg);
+        }
+        self.events.emit("client-moved", json!({ "seat": seat.key(), "id": client_id }));
+        // A ready client has to fetch the new seat's files first
+        emit_status(&self.events, &seat.key(), client_id, status);
+    }
+
+    async fn resolve_seat(&self, seat: &str) -> crate::Result<Seat> {
+        let config = self.app_state.session_config().await.ok_or(Error::NoSession)?;
+        seats::resolve(&config, seat)
+    }
+
+    /// WebSocket endpoint, client config and static client files, as served on `port`
+    fn routes(
+        &self,
+        port: Arc<OnceLock<u16>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone + Send + Sync + 'static {
+        let perf_state = self.perf_state.clone();
+        let app_state = self.app_state.clone();
+        let events = self.events.clone();
+        let clock = self.clock.clone();
+        let shutdown = self.shutdown.subscribe();
+
+        // Define the WebSocket route for /ws
+        let ws_route = warp::path("ws")
+            .and(warp::ws())
+            .map(move |ws: warp::ws::Ws| {
+                let perf_state = perf_state.clone();
+                let app_state = app_state.clone();
+                let events = events.clone();
+                let clock = clock.clone();
+                let mut shutdown = shutdown.clone();
+                ws.on_upgrade(move |socket| async move {
+                    let (mut tx, mut rx) = socket.split();
+                    let policy = perf_state.lock().await.slow_clients;
+                    let (sender, mut receiver) = outbox::channel(policy);
+
+                    // Writer task
+                    let writer = tokio::spawn(async move {
+                        while let Some(msg) = receiver.recv().await {
+                            let _ = tx.send(msg).await;
+                        }
+                    });
+
+                    // Reader loop, until the socket closes, the server stops, or the client is
+                    // dropped for lagging
+                    loop {
+                        let msg = tokio::select! {
+                            msg = rx.next() => msg,
+                            _ = stopped(&mut shutdown) => break,
+                            _ = sender.lagged() => {
+                                let lagging = {
+                                    let perf = perf_state.lock().await;
+                                    perf.seat_for_sender(&sender)
+                                        .and_then(|seat| Some((perf.seat_map.get(&seat)?.id.clone(), seat)))
+                                };
+                                if let Some((id, seat)) = lagging {
+                                    println!("[ws] seat {} is falling behind (client {}, {:?})", seat, id, policy);
+                                    events.emit("client-lagging", json!({ "seat": seat, "id": id, "policy": policy }));
+                                }
+                                if policy.disconnects() {
+                                    break;
+                                }
+                                continue;
+                            }
+                        };
+                        let Some(Ok(msg)) = msg else {
+                            break;
+                        };
+                        if let Ok(text) = msg.to_str() {
+                            if let Some(response) = handle_message(
+                                text,
+                                perf_state.clone(),
+                                app_state.clone(),
+                                sender.clone(),
+                                events.clone(),
+                                clock.clone(),
+                            )
+                            .await
+                            {
+                                let _ = sender.send(response);
+                            }
+                        }
+                    }
+                    // Dropping both halves of the socket closes it
+                    writer.abort();
+
+                    // Socket closed: keep the seat, but mark it as disconnected
+                    let now = clock.now_ms() as u64;
+                    let disconnected = perf_state.lock().await.mark_disconnected(&sender, now);
+                    if let Some((seat, id)) = disconnected {
+                        println!("[ws] seat {} disconnected (client {})", seat, id);
+                        events.emit("client-disconnected", json!({ "seat": seat, "id": id }));
+                    }
+                })
+            });
+
+        // The client reads its WebSocket port from /config.json, so answer with the port it came in on.
+        // `port` is filled in once the listener is bound.
+        let config_route = warp::path!("config.json").and(warp::get()).map(move || {
+            let mut config = std::fs::read_to_string("./static/config.json")
+                .ok()
+                .and_then(|s| serde_json::from_str::<serde_json::Map<String, Value>>(&s).ok())
+                .unwrap_or_default();
+            config.insert("wsPort".into(), json!(port.get()));
+            warp::reply::json(&config)
+        });
+
+        let static_files_route = warp::fs::dir("./static");
+        let routes = ws_route.or(config_route).or(static_files_route);
+        routes.with(warp::cors().allow_any_origin())
+    }
+
+    async fn restore_journal(&self, path: &Path) {
+        let journal = match Journal::load(path) {
+            Ok(Some(journal)) => journal,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("[journal] ignoring {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let now = self.clock.now_ms() as u64;
+        let restored = journal.restore(&mut *self.perf_state.lock().await, now);
+        if restored > 0 {
+            println!("[journal] restored {} seats from {}", restored, path.display());
+        }
+    }
+
+    /// Periodically write the seats and playing phase to the journal when they change
+    fn spawn_journal(&self, path: PathBuf) -> JoinHandle<()> {
+        let perf_state = self.perf_state.clone();
+
+        tokio::spawn(async move {
+            let mut written: Option<Journal> = None;
+            let mut failing = false;
+            let mut interval = tokio::time::interval(JOURNAL_INTERVAL);
+            loop {
+                interval.tick().await;
+
+                let journal = Journal::snapshot(&*perf_state.lock().await);
+                if written.as_ref() == Some(&journal) {
+                    continue;
+                }
+                match journal.save(&path) {
+                    Ok(()) => {
+                        written = Some(journal);
+                        failing = false;
+                    }
+                    // Once per run of failures, not every second
+                    Err(e) if !failing => {
+                        eprintln!("[journal] {}", e);
+                        failing = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+        })
+    }
+
+    /// Periodically expire stale client leases, notify them, and free their seats
+    fn spawn_reaper(&self) -> JoinHandle<()> {
+        let perf_state = self.perf_state.clone();
+        let events = self.events.clone();
+        let clock = self.clock.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REAPER_INTERVAL);
+            loop {
+                interval.tick().await;
+
+                let now = clock.now_ms() as u64;
+                let expired = perf_state.lock().await.expire_clients(now);
+
+                for (seat, info) in expired {
+                    println!("[reaper] seat {} expired (client {})", seat, info.id);
+                    if let Some(sender) = info.sender {
+                        let err = ServerMessage::error(ErrorCode::ClientIdInvalid, "Client ID is no longer valid");
+                        let _ = sender.send(err);
+                    }
+                    events.emit("seat-expired", json!({ "seat": seat, "id": info.id }));
+                }
+            }
+        })
+    }
+
+    /// Periodically send every connected client its drift-corrected clock offset.
+    /// Clients answer with a fresh time_request, which keeps their clock model current.
+    fn spawn_resync(&self) -> JoinHandle<()> {
+        let perf_state = self.perf_state.clone();
+        let resync_interval = self.resync_interval;
+        let clock = self.clock.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(resync_interval);
+            loop {
+                interval.tick().await;
+
+                let now = clock.now_ms();
+                let updates: Vec<_> = {
+                    let locked = perf_state.lock().await;
+                    locked
+                        .seat_map
+                        .values()
+                        .filter_map(|info| {
+                            let sender = info.sender.clone()?;
+                            let model = info.sync.clock_model();
+                            Some((sender, model))
+                        })
+                        .collect()
+                };
+
+                for (sender, model) in updates {
+                    let msg = ServerMessage::OffsetUpdate {
+                        offset_ms: model.map(|m| m.offset_at(now)),
+                        skew_ppm: model.map(|m| m.skew_ppm),
+                        server_time: now,
+                    };
+                    let _ = sender.send(msg);
+                }
+            }
+        })
+    }
+
+    /// Schedule a phase from the session on every client.
+    /// Returns the lead time (ms) between now and the scheduled start.
+    pub async fn start_phase(&self, phase_id: String) -> crate::Result<i64> {
+        // 1. Grab the phase out of AppState
+        let phase = self
+            .app_state
+            .phases
+            .lock()
+            .await
+            .get(&phase_id)
+            .cloned()
+            .ok_or_else(|| Error::PhaseNotFound(phase_id.clone()))?;
+
+        // 2. Turn Vec<SeatAssignment> → HashMap<seat_string, AssignmentPayload>
+        let assignments: HashMap<_, _> = phase
+            .assignments
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, sa)| match (sa.rnbo_id, sa.sheet_id) {
+                (Some(rnbo), Some(sheet)) => Some((
+                    i.to_string(),
+                    AssignmentPayload { rnbo_id: rnbo, sheet_id: sheet },
+                )),
+                _ => None,
+            })
+            .collect();
+
+        // 3. Start far enough ahead that the slowest client gets the downbeat in time
+        let (payload, lead_ms) = {
+            let mut perf = self.perf_state.lock().await;
+            let lead_ms = perf.schedule_lead_ms();
+            let start_time = self.clock.now_ms() + lead_ms;
+            let active = ActivePhase {
+                phase_id,
+                bpm: phase.bpm,
+                count_in: phase.count_in,
+                start_time,
+                assignments,
+            };
+            let payload = active.start_payload();
+            // Kept so clients that join later can catch up
+            perf.active_phase = Some(active);
+            (payload, lead_ms)
+        };
+
+        let payload = ServerMessage::PhaseStart(payload);
+
+        broadcast_to_all(self.perf_state.clone(), &payload).await;
+        println!("[phase_start] scheduled {} ms ahead", lead_ms);
+        Ok(lead_ms)
+    }
+
+    /// Tell every client to stop the current phase on the same beat.
+    /// Returns the scheduled stop time (server clock, ms).
+    pub async fn stop_phase(&self) -> i64 {
+        // Stop on the first beat boundary after the lead time, or right after it if nothing is playing
+        let mut perf = self.perf_state.lock().await;
+        let earliest = self.clock.now_ms() + perf.schedule_lead_ms();
+        let stop_time = match perf.active_phase.take() {
+            Some(active) => {
+                println!("[phase_stop] stopping phase {}", active.phase_id);
+                active.next_beat_at(earliest)
+            }
+            None => earliest,
+        };
+        drop(perf);
+
+        let payload = ServerMessage::PhaseStop(PhaseStopPayload { stop_time });
+        broadcast_to_all(self.perf_state.clone(), &payload).await;
+        stop_time
+    }
+
+    /// Resolves once the server is stopped
+    fn shutdown_signal(&self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let mut shutdown = self.shutdown.subscribe();
+        async move { stopped(&mut shutdown).await }
+    }
+
+    /// Stop the server, close every client connection and forget its clients. The join rules,
+    /// seat allocator and other settings stay, so the controller can be started again.
+    pub async fn stop(&mut self) {
+        // Connected sockets outlive the listeners, so close them too; clients rejoin with `rj`
+        self.shutdown.send_replace(true);
+        if let Some(reaper) = self.reaper.take() {
+            reaper.abort();
+        }
+        if let Some(resync) = self.resync.take() {
+            resync.abort();
+        }
+        if !self.servers.is_empty() {
+            for server in self.servers.drain(..) {
+                server.abort();
+            }
+            println!("🛑 WSS/HTTP server stopped");
+        }
+        if let Some(mdns) = self.mdns.take() {
+            // Waits briefly for the goodbye packets
+            tokio::task::spawn_blocking(move || mdns.withdraw()).await.ok();
+        }
+        self.info = None;
+        self.started_at = None;
+
+        let mut state = self.perf_state.lock().await;
+        // The last word goes to the journal, so a restart picks up where this left off
+        if let Some(journal) = self.journal.take() {
+            journal.abort();
+            if let Some(path) = &self.journal_path {
+                if let Err(e) = Journal::snapshot(&state).save(path) {
+                    eprintln!("[journal] {}", e);
+                }
+            }
+        }
+        state.reset();
+    }
+}
+
+/// Resolves once the server's shutdown flag is set
+async fn stopped(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stopped| *stopped).await;
+}
+
+/// What a started server is serving
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+pub struct ServerInfo {
+    /// Address to put in join URLs
+    pub address: String,
+    /// `.local` name advertised over mDNS, if advertising
+    pub hostname: Option<String>,
+    /// Where the audience opens the client: HTTPS when it's served, otherwise plain HTTP
+    pub join_url: String,
+    /// Address listened on, `0.0.0.0` for all interfaces
+    pub bind_address: String,
+    pub tls_port: Option<u16>,
+    pub plain_port: Option<u16>,
+    pub redirect_port: Option<u16>,
+    pub tls: Option<TlsInfo>,
+    /// Code the audience has to enter, shown next to the QR code
+    pub join_code: Option<String>,
+    pub require_seat_tokens: bool,
+}
+
+/// Snapshot for the admin UI's status display
+#[derive(Debug, Clone, Default, Serialize, TS, JsonSchema)]
+pub struct ServerStatus {
+    pub running: bool,
+    /// Address clients reach the server on
+    pub address: Option<String>,
+    pub info: Option<ServerInfo>,
+    #[ts(type = "number")]
+    pub uptime_ms: u64,
+    pub client_count: usize,
+}
+
+/// Plain HTTP listener that sends every request to the same path on the HTTPS port
+fn redirect_to_https(
+    tls_port: u16,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone + Send + Sync + 'static {
+    let raw_query = warp::query::raw().or(warp::any().map(String::new)).unify();
+
+    warp::header::optional::<String>("host")
+        .and(warp::path::full())
+        .and(raw_query)
+        .map(move |host: Option<String>, path: FullPath, query: String| {
+            let host = host.as_deref().map(strip_port).unwrap_or("localhost");
+            let query = if query.is_empty() { String::new() } else { format!("?{}", query) };
+            let location = format!("https://{}:{}{}{}", host, tls_port, path.as_str(), query);
+            match location.parse::<Uri>() {
+                Ok(uri) => warp::redirect::temporary(uri).into_response(),
+                Err(_) => StatusCode::BAD_REQUEST.into_response(),
+            }
+        })
+}
+
+/// `host:port` → `host`, keeping IPv6 brackets intact
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return host.find(']').map_or(host, |end| &host[..=end]);
+    }
+    host.split(':').next().unwrap_or(host)
+}
+
+/// Holds the running controller (or `None` if stopped)
+#[derive(Clone, Default)]
+pub struct ServerManager {
+    pub controller: Arc<Mutex<Option<ServerController>>>,
+    /// What the running controller was started with, to tell a repeated start from a restart
+    pub options: Arc<Mutex<Option<ServerOptions>>>,
+}
@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+
+/// How many recent samples are kept per client
+const MAX_SAMPLES: usize = 64;
+
+/// How many of the latest samples the scheduling RTT is taken over
+const RECENT_RTT_SAMPLES: usize = 8;
+
+/// Samples slower than the best RTT by more than this are left out of the drift fit
+const FIT_RTT_SLACK_MS: i64 = 10;
+
+/// Samples must span at least this long before a skew estimate is trusted
+const MIN_FIT_SPAN_MS: i64 = 10_000;
+
+/// Round trips slower than this say nothing useful about the clock
+const MAX_SAMPLE_RTT_MS: i64 = 10_000;
+
+/// A sample has to be reported within this long of the server answering its time_request
+const MAX_SAMPLE_AGE_MS: i64 = 60_000;
+
+/// One time_request round trip, reported back by the client once the reply arrives
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSample {
+    pub client_send: i64,    // client clock, ms
+    pub server_time: i64,    // server clock, ms
+    pub client_receive: i64, // client clock, ms
+}
+
+impl TimeSample {
+    pub fn rtt_ms(&self) -> i64 {
+        self.client_receive.saturating_sub(self.client_send)
+    }
+
+    /// Server clock minus client clock, assuming the path is symmetric
+    pub fn offset_ms(&self) -> f64 {
+        self.server_time as f64 - (self.client_send as f64 + self.client_receive as f64) / 2.0
+    }
+
+    /// All three timestamps come from the client. A sample is only believed if its server time
+    /// is one this server could have handed out in the last minute, and its RTT is neither
+    /// negative nor absurdly long. (0 ms is real on a fast LAN with millisecond clocks.)
+    pub fn is_plausible(&self, now: i64) -> bool {
+        let rtt = self.client_receive.checked_sub(self.client_send);
+        let age = now.checked_sub(self.server_time);
+        matches!(rtt, Some(0..=MAX_SAMPLE_RTT_MS)) && matches!(age, Some(0..=MAX_SAMPLE_AGE_MS))
+    }
+}
+
+/// Linear model of a client's clock: offset(t) = offset_ms + skew * (t - reference_time)
+#[derive(Debug, Clone, Copy)]
+pub struct ClockModel {
+    pub offset_ms: f64,      // server minus client at reference_time
+    pub skew_ppm: f64,       // how fast the offset drifts, in parts per million
+    pub reference_time: i64, // server clock, ms
+}
+
+impl ClockModel {
+    /// Predicted offset (server minus client, ms) at a server time
+    pub fn offset_at(&self, server_time: i64) -> f64 {
+        self.offset_ms + self.skew_ppm * 1e-6 * server_time.saturating_sub(self.reference_time) as f64
+    }
+}
+
+/// Per-client clock sync quality, estimated NTP-style from recent samples
+#[derive(Debug, Default, Clone)]
+pub struct SyncStats {
+    samples: VecDeque<TimeSample>,
+}
+
+impl SyncStats {
+    /// Add a sample, dropping the oldest once the window is full.
+    /// Returns false for samples that cannot be right (see [`TimeSample::is_plausible`]).
+    pub fn add_sample(&mut self, sample: TimeSample, now: i64) -> bool {
+        if !sample.is_plausible(now) {
+            return false;
+        }
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        true
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// The sample with the lowest RTT, which has the least path asymmetry to worry about
+    fn best_sample(&self) -> Option<&TimeSample> {
+        self.samples.iter().min_by_key(|s| s.rtt_ms())
+    }
+
+    pub fn best_rtt_ms(&self) -> Option<u64> {
+        self.best_sample().map(|s| s.rtt_ms() as u64)
+    }
+
+    /// Round trip of the latest sample, for showing how the connection is doing now
+    pub fn last_rtt_ms(&self) -> Option<u64> {
+        self.samples.back().map(|s| s.rtt_ms() as u64)
+    }
+
+    /// Slowest round trip among the latest samples. Cues are scheduled to cover this, so a phone
+    /// whose RTT swings still gets them in time.
+    pub fn recent_max_rtt_ms(&self) -> Option<u64> {
+        self.samples
+            .iter()
+            .rev()
+            .take(RECENT_RTT_SAMPLES)
+            .map(|s| s.rtt_ms() as u64)
+            .max()
+    }
+
+    /// Offset estimate (server minus client, ms) taken from the best sample
+    pub fn offset_ms(&self) -> Option<f64> {
+        self.best_sample().map(TimeSample::offset_ms)
+    }
+
+    /// Least-squares fit of offset against server time over the low-RTT samples.
+    /// Falls back to a zero-skew model until the samples span long enough.
+    pub fn clock_model(&self) -> Option<ClockModel> {
+        let best_rtt = self.best_sample()?.rtt_ms();
+        let fit: Vec<&TimeSample> = self
+            .samples
+            .iter()
+            .filter(|s| s.rtt_ms() <= best_rtt.saturating_add(FIT_RTT_SLACK_MS))
+            .collect();
+
+        let reference_time = fit.iter().map(|s| s.server_time).max()?;
+        let span = reference_time.saturating_sub(fit.iter().map(|s| s.server_time).min()?);
+        if fit.len() < 2 || span < MIN_FIT_SPAN_MS {
+            return Some(ClockModel {
+                offset_ms: self.offset_ms()?,
+                skew_ppm: 0.0,
+                reference_time,
+            });
+        }
+
+        let n = fit.len() as f64;
+        let xs: Vec<f64> = fit
+            .iter()
+            .map(|s| s.server_time.saturating_sub(reference_time) as f64)
+            .collect();
+        let ys: Vec<f64> = fit.iter().map(|s| s.offset_ms()).collect();
+        let mean_x = xs.iter().sum::<f64>() / n;
+        let mean_y = ys.iter().sum::<f64>() / n;
+        let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let slope = sxy / sxx;
+
+        Some(ClockModel {
+            offset_ms: mean_y - slope * mean_x,
+            skew_ppm: slope * 1e6,
+            reference_time,
+        })
+    }
+
+    /// Standard deviation of the per-sample offsets, in ms
+    pub fn jitter_ms(&self) -> Option<f64> {
+        if self.samples.len() < 2 {
+            return None;
+        }
+        let n = self.samples.len() as f64;
+        let mean = self.samples.iter().map(TimeSample::offset_ms).sum::<f64>() / n;
+        let variance = self
+            .samples
+            .iter()
+            .map(|s| (s.offset_ms() - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        Some(variance.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_800_000_000_000;
+
+    fn sample(client_send: i64, server_time: i64, client_receive: i64) -> TimeSample {
+        TimeSample { client_send, server_time, client_receive }
+    }
+
+    #[test]
+    fn keeps_plausible_samples() {
+        let mut stats = SyncStats::default();
+        assert!(stats.add_sample(sample(NOW - 40, NOW - 25, NOW - 10), NOW));
+        assert!(stats.add_sample(sample(NOW - 5, NOW - 5, NOW - 5), NOW));
+        assert_eq!(stats.best_rtt_ms(), Some(0));
+        assert_eq!(stats.last_rtt_ms(), Some(0));
+
+        assert!(stats.add_sample(sample(NOW - 90, NOW - 50, NOW - 10), NOW));
+        assert_eq!(stats.best_rtt_ms(), Some(0));
+        assert_eq!(stats.last_rtt_ms(), Some(80));
+    }
+
+    #[test]
+    fn rejects_crafted_samples() {
+        let mut stats = SyncStats::default();
+        // Timestamps chosen to overflow
+        assert!(!stats.add_sample(sample(i64::MIN, NOW, i64::MAX), NOW));
+        assert!(!stats.add_sample(sample(NOW, i64::MIN, NOW), NOW));
+        // A server time from the future or long ago
+        assert!(!stats.add_sample(sample(NOW, NOW + 1_000, NOW), NOW));
+        assert!(!stats.add_sample(sample(NOW, NOW - MAX_SAMPLE_AGE_MS - 1, NOW), NOW));
+        // Negative and absurd round trips
+        assert!(!stats.add_sample(sample(NOW, NOW, NOW - 1), NOW));
+        assert!(!stats.add_sample(sample(NOW - 1_000_000_000_000, NOW, NOW), NOW));
+        assert_eq!(stats.sample_count(), 0);
+        assert!(stats.clock_model().is_none());
+    }
+
+    /// A round trip with `rtt_ms` split evenly, answered at `server_time` by a client whose clock
+    /// is `offset_ms` behind the server's
+    fn symmetric(server_time: i64, offset_ms: f64, rtt_ms: i64) -> TimeSample {
+        let client_time = (server_time as f64 - offset_ms).round() as i64;
+        sample(client_time - rtt_ms / 2, server_time, client_time + rtt_ms / 2)
+    }
+
+    /// Ten minutes of samples every 10 s from a client 500 ms behind that drifts 100 ppm
+    fn drifting(stats: &mut SyncStats) {
+        for i in 0..60 {
+            let server_time = NOW + i * 10_000;
+            let offset = 500.0 + 100e-6 * (i * 10_000) as f64;
+            assert!(stats.add_sample(symmetric(server_time, offset, 20), server_time));
+        }
+    }
+
+    #[test]
+    fn fits_offset_and_drift() {
+        let mut stats = SyncStats::default();
+        drifting(&mut stats);
+
+        let model = stats.clock_model().expect("a model");
+        assert_eq!(model.reference_time, NOW + 590_000);
+        assert!((model.skew_ppm - 100.0).abs() < 1.0, "skew {}", model.skew_ppm);
+        assert!((model.offset_ms - 559.0).abs() < 0.5, "offset {}", model.offset_ms);
+        // Extrapolates along the drift
+        let later = model.offset_at(NOW + 1_190_000);
+        assert!((later - 619.0).abs() < 1.0, "offset a minute on {}", later);
+    }
+
+    #[test]
+    fn slow_samples_stay_out_of_the_fit() {
+        let mut stats = SyncStats::default();
+        drifting(&mut stats);
+        // Congested round trips, all of the delay on the way back
+        for i in 0..4 {
+            let server_time = NOW + 15_000 + i * 100_000;
+            let client_send = (server_time as f64 - 500.0).round() as i64 - 10;
+            assert!(stats.add_sample(sample(client_send, server_time, client_send + 400), server_time + 1));
+        }
+
+        let model = stats.clock_model().expect("a model");
+        assert!((model.skew_ppm - 100.0).abs() < 1.0, "skew {}", model.skew_ppm);
+    }
+
+    #[test]
+    fn no_skew_until_the_samples_span_long_enough() {
+        let mut stats = SyncStats::default();
+        for i in 0..5 {
+            let server_time = NOW + i * 1_000;
+            assert!(stats.add_sample(symmetric(server_time, 250.0 + i as f64, 20), server_time));
+        }
+        let model = stats.clock_model().expect("a model");
+        assert_eq!(model.skew_ppm, 0.0);
+        assert_eq!(model.offset_ms, stats.offset_ms().unwrap());
+    }
+
+    #[test]
+    fn samples_at_one_instant_dont_divide_by_zero() {
+        let mut stats = SyncStats::default();
+        for offset in [250.0, 260.0, 240.0] {
+            assert!(stats.add_sample(symmetric(NOW, offset, 20), NOW));
+        }
+        let model = stats.clock_model().expect("a model");
+        assert_eq!(model.skew_ppm, 0.0);
+        assert!(model.offset_ms.is_finite());
+        assert_eq!(model.reference_time, NOW);
+    }
+}
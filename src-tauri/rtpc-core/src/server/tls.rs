@@ -0,0 +1,223 @@
+use rcgen::{CertificateParams, DnType, KeyPair};
+use schemars::JsonSchema;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use ts_rs::TS;
+
+use crate::error::{Error, Result};
+
+/// Certificate locations used before certificates could be configured, relative to the working directory
+const LEGACY_CERT_PATH: &str = "certs/cert.pem";
+const LEGACY_KEY_PATH: &str = "certs/key.pem";
+
+/// Where the server's certificate comes from
+#[derive(Debug, Clone, Default)]
+pub struct TlsSource {
+    /// Explicit PEM files. Both or neither.
+    pub cert_path: Option<PathBuf>,
+    pub key_path: Option<PathBuf>,
+    /// Where self-signed certificates are generated and kept when no files are given
+    pub generated_dir: PathBuf,
+}
+
+/// What the admin UI shows about the certificate in use
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+pub struct TlsInfo {
+    pub cert_path: String,
+    pub key_path: String,
+    /// SHA-256 of the certificate, as browsers show it (AB:CD:...)
+    pub fingerprint: String,
+    pub self_signed: bool,
+}
+
+/// A validated certificate and key, ready to serve
+pub struct TlsIdentity {
+    pub cert_pem: Vec<u8>,
+    pub key_pem: Vec<u8>,
+    pub info: TlsInfo,
+}
+
+impl TlsSource {
+    /// Load the configured certificate, falling back to `certs/` and then to a self-signed
+    /// certificate for `lan_ip` (and the mDNS `hostname`, if any), generated on first use.
+    pub fn load(&self, lan_ip: Option<IpAddr>, hostname: Option<&str>) -> Result<TlsIdentity> {
+        match (&self.cert_path, &self.key_path) {
+            (Some(cert), Some(key)) => return load_identity(cert, key, false),
+            (None, None) => {}
+            _ => return Err(Error::Tls("Both a certificate and a key path are needed".into())),
+        }
+
+        let (legacy_cert, legacy_key) = (Path::new(LEGACY_CERT_PATH), Path::new(LEGACY_KEY_PATH));
+        if legacy_cert.exists() && legacy_key.exists() {
+            return load_identity(legacy_cert, legacy_key, false);
+        }
+
+        // One certificate per address and hostname, so the fingerprint stays the same on a given network
+        let host = lan_ip.map(|ip| ip.to_string()).unwrap_or_else(|| "localhost".into());
+        let mut file_stem = host.replace(':', "_");
+        if let Some(hostname) = hostname {
+            file_stem = format!("{}-{}", file_stem, hostname);
+        }
+        let cert = self.generated_dir.join(format!("self-signed-{}.cert.pem", file_stem));
+        let key = self.generated_dir.join(format!("self-signed-{}.key.pem", file_stem));
+        if !(cert.exists() && key.exists()) {
+            generate_self_signed(&host, hostname, &cert, &key)?;
+        }
+        load_identity(&cert, &key, true)
+    }
+}
+
+fn generate_self_signed(host: &str, hostname: Option<&str>, cert_path: &Path, key_path: &Path) -> Result<()> {
+    let mut names = vec![host.to_string()];
+    names.extend(hostname.map(String::from));
+    if host != "localhost" {
+        names.push("localhost".into());
+    }
+
+    let tls_err = |e: rcgen::Error| Error::Tls(format!("Failed to generate a certificate: {}", e));
+    let key_pair = KeyPair::generate().map_err(tls_err)?;
+    let mut params = CertificateParams::new(names).map_err(tls_err)?;
+    params.distinguished_name.push(DnType::CommonName, "RTPC");
+    let cert = params.self_signed(&key_pair).map_err(tls_err)?;
+
+    if let Some(dir) = cert_path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| Error::io(format!("Failed to create {}", dir.display()), e))?;
+    }
+    fs::write(cert_path, cert.pem())
+        .map_err(|e| Error::io(format!("Failed to write {}", cert_path.display()), e))?;
+    fs::write(key_path, key_pair.serialize_pem())
+        .map_err(|e| Error::io(format!("Failed to write {}", key_path.display()), e))?;
+
+    println!("[tls] generated a self-signed certificate for {} in {}", host, cert_path.display());
+    Ok(())
+}
+
+fn load_identity(cert_path: &Path, key_path: &Path, self_signed: bool) -> Result<TlsIdentity> {
+    let read = |path: &Path| {
+        fs::read(path).map_err(|e| Error::Tls(format!("Failed to read {}: {}", path.display(), e)))
+    };
+    let cert_pem = read(cert_path)?;
+    let key_pem = read(key_path)?;
+
+    let fingerprint = validate(&cert_pem, &key_pem)?;
+    Ok(TlsIdentity {
+        cert_pem,
+        key_pem,
+        info: TlsInfo {
+            cert_path: cert_path.display().to_string(),
+            key_path: key_path.display().to_string(),
+            fingerprint,
+            self_signed,
+        },
+    })
+}
+
+/// Check that the certificate and key parse and belong together. Returns the certificate's fingerprint.
+fn validate(cert_pem: &[u8], key_pem: &[u8]) -> Result<String> {
+    let certs = rustls_pemfile::certs(&mut &*cert_pem)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| Error::Tls(format!("Invalid certificate: {}", e)))?;
+    let leaf = certs
+        .first()
+        .ok_or_else(|| Error::Tls("No certificate found in the certificate file".into()))?;
+    let fingerprint = fingerprint(leaf);
+
+    let key = rustls_pemfile::private_key(&mut &*key_pem)
+        .map_err(|e| Error::Tls(format!("Invalid private key: {}", e)))?
+        .ok_or_else(|| Error::Tls("No private key found in the key file".into()))?;
+
+    rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| Error::Tls(e.to_string()))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| Error::Tls(format!("Certificate and key don't match: {}", e)))?;
+
+    Ok(fingerprint)
+}
+
+fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn source(test: &str) -> TlsSource {
+        let dir = std::env::temp_dir().join(format!("rtpc-tls-{}-{}", std::process::id(), test));
+        TlsSource { generated_dir: dir, ..Default::default() }
+    }
+
+    fn leaf_der(cert_pem: &[u8]) -> Vec<u8> {
+        rustls_pemfile::certs(&mut &*cert_pem).next().unwrap().unwrap().to_vec()
+    }
+
+    /// DER of a subjectAltName entry: [2] dNSName or [7] iPAddress, then the raw bytes
+    fn contains_san(der: &[u8], tag: u8, value: &[u8]) -> bool {
+        let mut entry = vec![tag, value.len() as u8];
+        entry.extend_from_slice(value);
+        der.windows(entry.len()).any(|w| w == entry)
+    }
+
+    #[test]
+    fn generates_a_lasting_certificate_for_the_address_and_hostname() {
+        let source = source("generated");
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10));
+        let first = source.load(Some(ip), Some("rtpc.local")).unwrap();
+        assert!(first.info.self_signed);
+        assert_eq!(first.info.fingerprint.split(':').count(), 32);
+
+        let der = leaf_der(&first.cert_pem);
+        assert!(contains_san(&der, 0x87, &[192, 0, 2, 10]));
+        assert!(contains_san(&der, 0x82, b"rtpc.local"));
+        assert!(contains_san(&der, 0x82, b"localhost"));
+
+        // The next start on this network reuses it, so browsers that accepted it keep trusting it
+        let again = source.load(Some(ip), Some("rtpc.local")).unwrap();
+        assert_eq!(again.info.fingerprint, first.info.fingerprint);
+
+        let explicit = TlsSource {
+            cert_path: Some(first.info.cert_path.clone().into()),
+            key_path: Some(first.info.key_path.clone().into()),
+            ..Default::default()
+        };
+        let loaded = explicit.load(None, None).unwrap();
+        assert!(!loaded.info.self_signed);
+        assert_eq!(loaded.info.fingerprint, first.info.fingerprint);
+        fs::remove_dir_all(&source.generated_dir).unwrap();
+    }
+
+    #[test]
+    fn needs_both_a_certificate_and_a_key() {
+        let cert_only = TlsSource { cert_path: Some("cert.pem".into()), ..Default::default() };
+        assert!(matches!(cert_only.load(None, None), Err(Error::Tls(_))));
+        let key_only = TlsSource { key_path: Some("key.pem".into()), ..Default::default() };
+        assert!(matches!(key_only.load(None, None), Err(Error::Tls(_))));
+    }
+
+    #[test]
+    fn rejects_a_key_from_another_certificate() {
+        let source = source("mismatch");
+        let a = source.load(Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))), None).unwrap();
+        let b = source.load(Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))), None).unwrap();
+        let crossed = TlsSource {
+            cert_path: Some(a.info.cert_path.into()),
+            key_path: Some(b.info.key_path.into()),
+            ..Default::default()
+        };
+        let result = crossed.load(None, None);
+        fs::remove_dir_all(&source.generated_dir).unwrap();
+        assert!(matches!(result, Err(Error::Tls(_))));
+    }
+}
@@ -0,0 +1,83 @@
+//! Moving and swapping clients from the admin side.
+
+mod common;
+
+use common::{controller, next_message, seat_client};
+use rtpc_core::events::EventSink;
+use rtpc_core::performance_types::ClientStatus;
+use rtpc_core::Error;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+/// Keeps every event for the test to look at
+#[derive(Default)]
+struct Recorded(Mutex<Vec<(String, Value)>>);
+
+impl EventSink for Recorded {
+    fn emit(&self, event: &str, payload: Value) {
+        self.0.lock().unwrap().push((event.to_string(), payload));
+    }
+}
+
+impl Recorded {
+    fn named(&self, name: &str) -> Vec<Value> {
+        let events = self.0.lock().unwrap();
+        events.iter().filter(|(event, _)| event == name).map(|(_, payload)| payload.clone()).collect()
+    }
+}
+
+#[tokio::test]
+async fn swapping_resets_status_and_tells_the_admin() {
+    let mut ctrl = controller().await;
+    let events = Arc::new(Recorded::default());
+    ctrl.events = events.clone();
+    let mut first = seat_client(&ctrl, "0", 20).await;
+    let _second = seat_client(&ctrl, "4", 20).await;
+    ctrl.perf_state.lock().await.seat_map.get_mut("0").unwrap().status = ClientStatus::Ready;
+
+    ctrl.swap_seats("A1", "B2").await.expect("seats swap");
+
+    let msg = next_message(&mut first).await;
+    assert_eq!(msg["type"], "seat_changed");
+    assert_eq!(msg["seat"], "4");
+    assert_eq!(msg["row"], "B");
+    assert_eq!(msg["column"], 2);
+
+    let mut statuses = events.named("client-status");
+    statuses.sort_by_key(|status| status["seat"].to_string());
+    assert_eq!(
+        statuses,
+        vec![
+            json!({ "seat": "0", "id": "client-4", "status": "joined" }),
+            json!({ "seat": "4", "id": "client-0", "status": "synced" }),
+        ]
+    );
+    assert_eq!(events.named("client-moved").len(), 2);
+}
+
+#[tokio::test]
+async fn swapping_a_seat_with_itself_is_a_no_op() {
+    let mut ctrl = controller().await;
+    let events = Arc::new(Recorded::default());
+    ctrl.events = events.clone();
+    let _client = seat_client(&ctrl, "0", 20).await;
+
+    ctrl.swap_seats("A1", "0").await.expect("nothing to do");
+    assert!(events.0.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn moves_only_to_empty_seats_in_the_room() {
+    let ctrl = controller().await;
+    let _first = seat_client(&ctrl, "0", 20).await;
+    let _second = seat_client(&ctrl, "1", 20).await;
+
+    assert!(matches!(ctrl.move_client("client-0", "A2").await, Err(Error::SeatTaken(_))));
+    assert!(matches!(ctrl.move_client("client-0", "C1").await, Err(Error::InvalidSeat { .. })));
+    assert!(matches!(ctrl.move_client("client-0", "6").await, Err(Error::InvalidSeat { .. })));
+    assert!(matches!(ctrl.swap_seats("A1", "Z9").await, Err(Error::InvalidSeat { .. })));
+
+    ctrl.move_client("client-0", "B3").await.expect("client moves");
+    assert_eq!(ctrl.client_at("B3").await.unwrap(), "client-0");
+    assert!(matches!(ctrl.client_at("A1").await, Err(Error::SeatEmpty(_))));
+}
@@ -0,0 +1,50 @@
+//! The committed TypeScript definitions and JSON Schemas match the Rust types.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rtpc_core::bindings::{self, GENERATED_DIRS};
+
+/// File name → contents for every file in `dir`
+fn files(dir: &Path) -> BTreeMap<String, String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return BTreeMap::new();
+    };
+    entries
+        .map(|entry| entry.expect("readable directory").path())
+        .filter(|path| path.is_file())
+        .map(|path| {
+            let name = path.file_name().expect("file name").to_string_lossy().into_owned();
+            (name, fs::read_to_string(&path).expect("readable file"))
+        })
+        .collect()
+}
+
+#[test]
+fn committed_bindings_are_up_to_date() {
+    let out: PathBuf = std::env::temp_dir().join(format!("rtpc-bindings-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&out);
+    bindings::export_all(&out).expect("bindings export");
+
+    let root = bindings::repo_root();
+    let mut stale = Vec::new();
+    for dir in GENERATED_DIRS {
+        let expected = files(&out.join(dir));
+        let committed = files(&root.join(dir));
+        let names: BTreeSet<&String> = expected.keys().chain(committed.keys()).collect();
+        stale.extend(
+            names
+                .into_iter()
+                .filter(|name| expected.get(*name) != committed.get(*name))
+                .map(|name| format!("{}/{}", dir, name)),
+        );
+    }
+    let _ = fs::remove_dir_all(&out);
+
+    assert!(
+        stale.is_empty(),
+        "generated files are out of date, run `cargo run -p rtpc-core --bin export-bindings`:\n  {}",
+        stale.join("\n  ")
+    );
+}
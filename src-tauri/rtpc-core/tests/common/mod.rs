@@ -0,0 +1,81 @@
+//! Session and client helpers shared by the integration tests.
+// Each test binary uses only some of these
+#![allow(dead_code)]
+
+use rtpc_core::clock::{FixedClock, SharedClock};
+use rtpc_core::events::StdoutEvents;
+use rtpc_core::outbox::{self, ClientReceiver, SlowClientPolicy};
+use rtpc_core::performance_types::ClientInfo;
+use rtpc_core::server_controller::ServerController;
+use rtpc_core::state::{AppState, SessionSaveState};
+use rtpc_core::time_sync::TimeSample;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const SAFETY_MS: u64 = 250;
+pub const TTL_MS: u64 = 3_600_000;
+
+/// Two rows of three, and a 120 bpm phase with a one-bar count-in and a part for the first seat
+pub fn session() -> SessionSaveState {
+    let mut assignments = vec![json!({ "rnbo_id": "patch", "sheet_id": "sheet" })];
+    assignments.resize(6, json!({ "rnbo_id": null, "sheet_id": null }));
+    serde_json::from_value(json!({
+        "config": { "name": "test", "path": "", "rows": 2, "columns": 3 },
+        "selected_file": null,
+        "rnbo_patches": [],
+        "sheet_music": [],
+        "phases": {
+            "p1": { "name": "Intro", "assignments": assignments, "bpm": 120, "count_in": 4, "index": 0 }
+        },
+        "current_phase_id": null
+    }))
+    .expect("valid session")
+}
+
+/// A stopped controller for [`session`], on the monotonic clock
+pub async fn controller() -> ServerController {
+    let app_state = Arc::new(AppState::default());
+    app_state.apply_session(session()).await;
+    ServerController::new(
+        TTL_MS,
+        SAFETY_MS,
+        Duration::from_secs(30),
+        app_state,
+        Arc::new(StdoutEvents),
+    )
+}
+
+/// A stopped controller for [`session`] on a clock the test moves by hand
+pub async fn controller_at(clock: Arc<FixedClock>) -> ServerController {
+    controller().await.with_clock(clock as SharedClock)
+}
+
+/// Put a connected client in `seat` whose one time sample took `rtt_ms`, as if it had joined
+pub async fn seat_client(ctrl: &ServerController, seat: &str, rtt_ms: i64) -> ClientReceiver {
+    let (sender, receiver) = outbox::channel(SlowClientPolicy::default());
+    let now = ctrl.clock.now_ms();
+    let mut info = ClientInfo {
+        id: format!("client-{}", seat),
+        sender: Some(sender),
+        expires_at: now as u64 + TTL_MS,
+        ..Default::default()
+    };
+    let sample = TimeSample {
+        client_send: now - rtt_ms,
+        server_time: now - rtt_ms / 2,
+        client_receive: now,
+    };
+    assert!(info.sync.add_sample(sample, now));
+
+    let mut perf = ctrl.perf_state.lock().await;
+    perf.id_map.insert(info.id.clone(), seat.to_string());
+    perf.seat_map.insert(seat.to_string(), info);
+    receiver
+}
+
+/// The next message queued for a client, as JSON
+pub async fn next_message(receiver: &mut ClientReceiver) -> Value {
+    let msg = receiver.recv().await.expect("a message");
+    serde_json::from_str(msg.to_str().expect("text")).expect("JSON")
+}
@@ -0,0 +1,96 @@
+//! Seat leases expiring and being refreshed, on a clock the test moves.
+
+mod common;
+
+use common::{controller_at, TTL_MS};
+use rtpc_core::clock::FixedClock;
+use rtpc_core::handlers::handle_message;
+use rtpc_core::outbox::{self, ClientReceiver, ClientSender, SlowClientPolicy};
+use rtpc_core::protocol::PROTOCOL_VERSION;
+use rtpc_core::server_controller::ServerController;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const NOW: i64 = 1_800_000_000_000;
+
+/// Handle one message from a client as the broker would, returning the reply
+async fn request(ctrl: &ServerController, sender: &ClientSender, msg: Value) -> Value {
+    let reply = handle_message(
+        &msg.to_string(),
+        ctrl.perf_state.clone(),
+        ctrl.app_state.clone(),
+        sender.clone(),
+        ctrl.events.clone(),
+        ctrl.clock.clone(),
+    )
+    .await
+    .expect("a reply");
+    serde_json::from_str(reply.to_str().expect("text")).expect("JSON")
+}
+
+/// Join seat A1 and return the connection and client ID
+async fn join(ctrl: &ServerController) -> (ClientSender, ClientReceiver, String) {
+    let (sender, receiver) = outbox::channel(SlowClientPolicy::default());
+    let joined = request(ctrl, &sender, json!({ "type": "j", "seat": "A1", "protocol": PROTOCOL_VERSION })).await;
+    assert_eq!(joined["type"], "joined");
+    assert_eq!(joined["expiresAt"], ctrl.clock.now_ms() as u64 + TTL_MS);
+    let id = joined["id"].as_str().expect("client ID").to_string();
+    (sender, receiver, id)
+}
+
+#[tokio::test]
+async fn a_refresh_extends_the_lease() {
+    let clock = Arc::new(FixedClock::new(NOW));
+    let ctrl = controller_at(clock.clone()).await;
+    let (sender, _receiver, id) = join(&ctrl).await;
+
+    clock.advance(TTL_MS as i64 - 1);
+    let refreshed = request(&ctrl, &sender, json!({ "type": "refresh", "id": id })).await;
+    assert_eq!(refreshed["type"], "refreshed");
+    assert_eq!(refreshed["expiresAt"], NOW as u64 + 2 * TTL_MS - 1);
+
+    // Past the first lease, but not the refreshed one
+    clock.advance(TTL_MS as i64 - 1);
+    let now = NOW as u64 + 2 * TTL_MS - 2;
+    assert!(ctrl.perf_state.lock().await.expire_clients(now).is_empty());
+
+    let expired = ctrl.perf_state.lock().await.expire_clients(now + 1);
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].0, "0");
+    assert_eq!(expired[0].1.id, id);
+}
+
+#[tokio::test]
+async fn an_expired_lease_frees_the_seat() {
+    let clock = Arc::new(FixedClock::new(NOW));
+    let ctrl = controller_at(clock.clone()).await;
+    let (sender, _receiver, id) = join(&ctrl).await;
+
+    clock.advance(TTL_MS as i64);
+    let expired = ctrl.perf_state.lock().await.expire_clients(NOW as u64 + TTL_MS);
+    assert_eq!(expired.len(), 1);
+    {
+        let perf = ctrl.perf_state.lock().await;
+        assert!(perf.seat_map.is_empty());
+        assert!(perf.id_map.is_empty());
+    }
+
+    let refused = request(&ctrl, &sender, json!({ "type": "refresh", "id": id })).await;
+    assert_eq!(refused["code"], "client_id_invalid");
+
+    // Someone else can take the seat now
+    let (_other, _receiver, other_id) = join(&ctrl).await;
+    assert_ne!(other_id, id);
+}
+
+#[tokio::test]
+async fn a_late_refresh_is_refused_before_the_reaper_runs() {
+    let clock = Arc::new(FixedClock::new(NOW));
+    let ctrl = controller_at(clock.clone()).await;
+    let (sender, _receiver, id) = join(&ctrl).await;
+
+    clock.advance(TTL_MS as i64);
+    let refused = request(&ctrl, &sender, json!({ "type": "refresh", "id": id })).await;
+    assert_eq!(refused["type"], "error");
+    assert_eq!(refused["code"], "client_id_invalid");
+}
@@ -0,0 +1,158 @@
+//! Runs the broker on a free plain WS port and talks to it like a phone would.
+
+mod common;
+
+use futures_util::{SinkExt, StreamExt};
+use rtpc_core::access::JoinAccess;
+use rtpc_core::protocol::PROTOCOL_VERSION;
+use rtpc_core::server_controller::ServerController;
+use rtpc_core::tls::TlsSource;
+use rtpc_core::Listen;
+use serde_json::{json, Value};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const WAIT: Duration = Duration::from_secs(2);
+
+/// Plain WS on a free port on localhost
+fn localhost() -> Listen {
+    let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    Listen {
+        bind_ip: localhost,
+        advertise_ip: localhost,
+        tls_port: None,
+        plain_port: Some(0),
+        redirect_port: None,
+        mdns_hostname: None,
+        join_by_hostname: false,
+    }
+}
+
+/// A running controller on a free port on localhost, and that port
+async fn start() -> (ServerController, u16) {
+    let mut ctrl = common::controller().await;
+    let info = ctrl.start(&localhost(), &TlsSource::default()).await.expect("server starts");
+    (ctrl, info.plain_port.expect("plain port"))
+}
+
+async fn connect(port: u16) -> Socket {
+    let (socket, _) = tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}/ws", port))
+        .await
+        .expect("connects");
+    socket
+}
+
+async fn send(socket: &mut Socket, msg: Value) {
+    socket.send(Message::text(msg.to_string())).await.expect("sends");
+}
+
+/// The next text message, skipping anything else
+async fn recv(socket: &mut Socket) -> Value {
+    loop {
+        let msg = timeout(WAIT, socket.next())
+            .await
+            .expect("a reply in time")
+            .expect("socket open")
+            .expect("valid frame");
+        if let Message::Text(text) = msg {
+            return serde_json::from_str(&text).expect("JSON reply");
+        }
+    }
+}
+
+#[tokio::test]
+async fn stop_closes_connected_sockets() {
+    let (mut ctrl, port) = start().await;
+    let mut socket = connect(port).await;
+    send(&mut socket, json!({ "type": "ping" })).await;
+    assert_eq!(recv(&mut socket).await["type"], "pong");
+
+    ctrl.stop().await;
+
+    // The socket closes instead of being answered by the stopped broker
+    let next = timeout(WAIT, socket.next()).await.expect("socket closed after stop");
+    assert!(
+        matches!(next, None | Some(Err(_)) | Some(Ok(Message::Close(_)))),
+        "unexpected message after stop: {:?}",
+        next
+    );
+}
+
+/// The next message of type `ty`, skipping file manifests and the like
+async fn recv_type(socket: &mut Socket, ty: &str) -> Value {
+    loop {
+        let msg = recv(socket).await;
+        if msg["type"] == ty {
+            return msg;
+        }
+    }
+}
+
+#[tokio::test]
+async fn joins_a_seat_and_answers_pings() {
+    let (mut ctrl, port) = start().await;
+    let mut socket = connect(port).await;
+
+    send(&mut socket, json!({ "type": "j", "seat": "B2", "protocol": PROTOCOL_VERSION })).await;
+    let joined = recv(&mut socket).await;
+    assert_eq!(joined["type"], "joined");
+    assert_eq!(joined["seat"], "4");
+    assert_eq!(joined["row"], "B");
+    assert_eq!(joined["column"], 2);
+    assert_eq!(joined["protocol"], PROTOCOL_VERSION);
+    assert!(joined["id"].as_str().is_some_and(|id| !id.is_empty()));
+
+    send(&mut socket, json!({ "type": "ping" })).await;
+    recv_type(&mut socket, "pong").await;
+
+    // The same seat by index is already taken
+    let mut other = connect(port).await;
+    send(&mut other, json!({ "type": "j", "seat": 4, "protocol": PROTOCOL_VERSION })).await;
+    let taken = recv(&mut other).await;
+    assert_eq!(taken["type"], "error");
+    assert_eq!(taken["code"], "seat_taken");
+
+    ctrl.stop().await;
+}
+
+#[tokio::test]
+async fn rejects_seats_outside_the_room() {
+    let (mut ctrl, port) = start().await;
+    let mut socket = connect(port).await;
+
+    for seat in [json!("C1"), json!("A4"), json!(6), json!("nowhere")] {
+        send(&mut socket, json!({ "type": "j", "seat": seat, "protocol": PROTOCOL_VERSION })).await;
+        let reply = recv(&mut socket).await;
+        assert_eq!(reply["type"], "error", "seat {}", seat);
+        assert_eq!(reply["code"], "invalid_seat", "seat {}", seat);
+    }
+
+    // An old client is told to update rather than seated
+    send(&mut socket, json!({ "type": "j", "seat": "A1", "protocol": PROTOCOL_VERSION - 1 })).await;
+    assert_eq!(recv(&mut socket).await["code"], "protocol_mismatch");
+
+    ctrl.stop().await;
+}
+
+#[tokio::test]
+async fn a_restarted_controller_keeps_its_settings() {
+    let (mut ctrl, _) = start().await;
+    ctrl.set_join_access(JoinAccess::new(Some("1234".into()), false, None)).await;
+    ctrl.stop().await;
+
+    let info = ctrl.start(&localhost(), &TlsSource::default()).await.expect("server restarts");
+    let mut socket = connect(info.plain_port.expect("plain port")).await;
+    send(&mut socket, json!({ "type": "j", "seat": "A1", "protocol": PROTOCOL_VERSION })).await;
+    assert_eq!(recv(&mut socket).await["code"], "join_code_required");
+
+    send(&mut socket, json!({ "type": "j", "seat": "A1", "protocol": PROTOCOL_VERSION, "code": "1234" })).await;
+    assert_eq!(recv(&mut socket).await["type"], "joined");
+
+    ctrl.stop().await;
+}
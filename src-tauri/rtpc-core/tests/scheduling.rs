@@ -0,0 +1,115 @@
+//! Phase start and stop times against a fixed clock, with clients seated by hand.
+
+mod common;
+
+use common::{controller_at, next_message, seat_client, SAFETY_MS};
+use rtpc_core::clock::{Clock, FixedClock};
+use rtpc_core::handlers::handle_message;
+use rtpc_core::outbox::{self, ClientSender, SlowClientPolicy};
+use rtpc_core::protocol::PROTOCOL_VERSION;
+use rtpc_core::server_controller::ServerController;
+use serde_json::{json, Value};
+use warp::ws::Message;
+use std::sync::Arc;
+
+const NOW: i64 = 1_800_000_000_000;
+
+/// Handle one message from a client as the broker would
+async fn send(ctrl: &ServerController, sender: &ClientSender, msg: Value) -> Option<Message> {
+    handle_message(
+        &msg.to_string(),
+        ctrl.perf_state.clone(),
+        ctrl.app_state.clone(),
+        sender.clone(),
+        ctrl.events.clone(),
+        ctrl.clock.clone(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn start_leads_by_the_worst_rtt_plus_safety() {
+    let clock = Arc::new(FixedClock::new(NOW));
+    let ctrl = controller_at(clock).await;
+    let mut near = seat_client(&ctrl, "0", 40).await;
+    let _far = seat_client(&ctrl, "1", 120).await;
+
+    let lead = ctrl.start_phase("p1".into()).await.expect("phase starts");
+    assert_eq!(lead, 120 + SAFETY_MS as i64);
+
+    let msg = next_message(&mut near).await;
+    assert_eq!(msg["type"], "phase_start");
+    assert_eq!(msg["start_time"], NOW + lead);
+    assert_eq!(msg["assignments"]["0"]["rnbo_id"], "patch");
+}
+
+#[tokio::test]
+async fn a_very_slow_client_does_not_delay_everyone() {
+    let clock = Arc::new(FixedClock::new(NOW));
+    let ctrl = controller_at(clock).await;
+    let _near = seat_client(&ctrl, "0", 40).await;
+    let _stalled = seat_client(&ctrl, "1", 8_000).await;
+
+    let lead = ctrl.start_phase("p1".into()).await.expect("phase starts");
+    assert_eq!(lead, 40 + SAFETY_MS as i64);
+}
+
+#[tokio::test]
+async fn stop_lands_on_the_first_beat_after_the_lead() {
+    let clock = Arc::new(FixedClock::new(NOW));
+    let ctrl = controller_at(clock.clone()).await;
+    let mut client = seat_client(&ctrl, "0", 100).await;
+
+    // Beats fall every 500 ms from the start time
+    let lead = ctrl.start_phase("p1".into()).await.expect("phase starts");
+    let start_time = NOW + lead;
+    next_message(&mut client).await;
+
+    clock.advance(3_100);
+    let stop_time = ctrl.stop_phase().await;
+    // The lead runs to start + 3 450, so the stop waits for the beat at start + 3 500
+    assert_eq!(stop_time, start_time + 3_500);
+
+    let msg = next_message(&mut client).await;
+    assert_eq!(msg["type"], "phase_stop");
+    assert_eq!(msg["stop_time"], stop_time);
+    assert!(ctrl.perf_state.lock().await.active_phase.is_none());
+
+    // With nothing playing, a stop goes out one lead from now
+    assert_eq!(ctrl.stop_phase().await, clock.now_ms() + lead);
+}
+
+#[tokio::test]
+async fn joining_during_a_phase_gets_a_catch_up() {
+    let clock = Arc::new(FixedClock::new(NOW));
+    let ctrl = controller_at(clock.clone()).await;
+    let _playing = seat_client(&ctrl, "0", 100).await;
+    let lead = ctrl.start_phase("p1".into()).await.expect("phase starts");
+    // Past the four-beat count-in and two beats into the phase
+    clock.advance(lead + 3_000);
+
+    // Both replies go out through the connection, the join first
+    let (sender, mut receiver) = outbox::channel(SlowClientPolicy::default());
+    let join = json!({ "type": "j", "seat": "A2", "protocol": PROTOCOL_VERSION });
+    assert!(send(&ctrl, &sender, join).await.is_none());
+    let joined = next_message(&mut receiver).await;
+    assert_eq!(joined["type"], "joined");
+    let catch_up = next_message(&mut receiver).await;
+    assert_eq!(catch_up["type"], "phase_catch_up");
+    assert_eq!(catch_up["start_time"], NOW + lead);
+    assert_eq!(catch_up["current_beat"], 2.0);
+
+    // A reconnect after the page reloads gets one as well
+    let (sender, mut receiver) = outbox::channel(SlowClientPolicy::default());
+    let rejoin = json!({ "type": "rj", "id": joined["id"], "protocol": PROTOCOL_VERSION });
+    assert!(send(&ctrl, &sender, rejoin).await.is_none());
+    assert_eq!(next_message(&mut receiver).await["type"], "joined");
+    assert_eq!(next_message(&mut receiver).await["type"], "phase_catch_up");
+
+    ctrl.stop_phase().await;
+    clock.advance(10_000);
+    let (sender, _receiver) = outbox::channel(SlowClientPolicy::default());
+    let rejoin = json!({ "type": "rj", "id": joined["id"], "protocol": PROTOCOL_VERSION });
+    let reply = send(&ctrl, &sender, rejoin).await.expect("a reply");
+    assert!(reply.to_str().expect("text").contains(r#""type":"joined""#));
+}
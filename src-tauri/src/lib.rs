@@ -0,0 +1,63 @@
+pub mod design_commands;
+pub mod server_commands;
+pub mod tauri_events;
+
+use std::sync::Arc;
+
+use rtpc_core::state::AppState;
+use rtpc_core::server_controller::ServerManager;
+pub use design_commands::*;
+pub use server_commands::*;
+
+
+/// Run the admin app: design UI plus the broker, controlled from the window
+pub fn run() {
+    let app_state = Arc::new(AppState::default());
+
+    tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
+        .manage(app_state)
+        .manage(ServerManager::default())
+        .invoke_handler(tauri::generate_handler![
+            set_session_config,
+            get_session_config,
+            add_phase,
+            remove_phase,
+            edit_phase,
+            set_current_phase,
+            get_current_phase,
+            clear_current_phase,
+            add_rnbo_file,
+            remove_rnbo_file,
+            add_sheet_file,
+            remove_sheet_file,
+            select_palette_file,
+            clear_selected_file,
+            assign_selected_file_to_seat,
+            unassign_file_from_seat,
+            get_selected_file,
+            get_assignments_for_phase,
+            get_rnbo_item,
+            get_sheet_item,
+            save_session_to_file,
+            load_session_from_file,
+            get_app_state,
+
+            start_server,
+            stop_server,
+            get_server_status,
+            get_local_ip,
+            list_network_interfaces,
+            get_connected_clients,
+            kick_client,
+            move_client,
+            swap_seats,
+            get_join_qr,
+            export_seat_qr_sheet,
+            broadcast_json,
+            broadcast_phase_start,
+            broadcast_phase_stop
+        ])
+        .run(tauri::generate_context!())
+        .expect("Error while running Tauri application.");
+}
\ No newline at end of file
@@ -1,43 +1,111 @@
 // src/server_commands/mod.rs
+// Thin Tauri wrappers over the broker in rtpc_core::server
 
-pub mod server_controller;
-pub mod performance_types;
-pub mod handlers;
+use std::path::Path;
 use std::sync::Arc;
-use chrono::Utc;
 use local_ip_address::local_ip;
-use serde_json::{json, Value};
-use tauri::State;
-use crate::state::{AppState, PhaseStartPayload, AssignmentPayload};
-use std::collections::HashMap;
+use serde_json::Value;
+use tauri::{Manager, State};
 
-use self::server_controller::{ServerController, ServerManager};
-use self::handlers::broadcast_to_all;
+use rtpc_core::handlers::broadcast_to_all;
+use rtpc_core::journal;
+use rtpc_core::network::{list_interfaces, NetworkInterface};
+use rtpc_core::performance_types::ClientSummary;
+use rtpc_core::qr::JoinQr;
+use rtpc_core::server_controller::{ServerController, ServerInfo, ServerManager, ServerStatus};
+use rtpc_core::state::AppState;
+use rtpc_core::ServerOptions;
 
-/// Start the TLS-enabled WebSocket server
+use crate::tauri_events::TauriEvents;
+
+/// Start the WebSocket server over TLS, plain WS, or both. Starting again with the same options
+/// returns what is already being served; new options restart the server, and if the new one
+/// can't start, the old one comes back.
+/// Without a cert and key path, a self-signed certificate is generated and kept in the app data dir.
 #[tauri::command]
 pub async fn start_server(
+    app: tauri::AppHandle,
     manager: State<'_, ServerManager>,
     app_state: State<'_, Arc<AppState>>,
-    ws_port: u16,
-    ttl_ms: u64,
-) -> Result<(), String> {
-    let mut ctrl = ServerController::new(ttl_ms, app_state.inner().clone());
-    ctrl.start_tls(ws_port)?;
-    *manager.controller.lock().await = Some(ctrl);
-    Ok(())
+    options: ServerOptions,
+) -> Result<ServerInfo, String> {
+    let listen = options.listen().map_err(|e| e.to_string())?;
+    let generated_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to find the app data directory: {}", e))?
+        .join("certs");
+
+    // Hold the locks for the whole restart so two starts can't race for the ports
+    let mut current = manager.controller.lock().await;
+    let mut started_with = manager.options.lock().await;
+    if let (Some(ctrl), Some(running)) = (current.as_ref(), started_with.as_ref()) {
+        if *running == options {
+            if let Some(info) = ctrl.info.clone() {
+                return Ok(info);
+            }
+        }
+    }
+    if let Some(old) = current.as_mut() {
+        old.stop().await;
+    }
+
+    let mut ctrl = ServerController::new(
+        options.ttl_ms,
+        options.safety_ms(),
+        options.resync_interval(),
+        app_state.inner().clone(),
+        Arc::new(TauriEvents(app)),
+    );
+    let seat_secret = app_state.seat_secret().await;
+    ctrl.set_join_access(options.join_access(seat_secret.as_deref())).await;
+    ctrl.set_seat_allocator(options.seat_allocator()).await;
+    ctrl.set_slow_client_policy(options.slow_clients.unwrap_or_default()).await;
+    ctrl.set_journal_path(app_state.session_file().await.as_deref().and_then(journal::path_for));
+    match ctrl.start(&listen, &options.tls_source(generated_dir.clone())).await {
+        Ok(info) => {
+            *current = Some(ctrl);
+            *started_with = Some(options);
+            Ok(info)
+        }
+        Err(e) => {
+            // Bring the previous server back, so a typo in the settings doesn't end the show
+            if let (Some(old), Some(old_options)) = (current.as_mut(), started_with.as_ref()) {
+                let restarted = match old_options.listen() {
+                    Ok(old_listen) => old.start(&old_listen, &old_options.tls_source(generated_dir)).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(restart_error) = restarted {
+                    eprintln!("[server] the previous server didn't come back: {}", restart_error);
+                    *current = None;
+                    *started_with = None;
+                }
+            }
+            Err(e.to_string())
+        }
+    }
 }
 
-// Stop the server and reset state
+// Stop the server and reset state. Stopping a stopped server is a no-op.
 #[tauri::command]
 pub async fn stop_server(
     manager: State<'_, ServerManager>,
 ) -> Result<(), String> {
-    if let Some(ctrl) = manager.controller.lock().await.as_mut() {
+    if let Some(mut ctrl) = manager.controller.lock().await.take() {
         ctrl.stop().await;
-        Ok(())
-    } else {
-        Err("Server not running".into())
+    }
+    *manager.options.lock().await = None;
+    Ok(())
+}
+
+/// Running state, listening ports, uptime and connected client count
+#[tauri::command]
+pub async fn get_server_status(
+    manager: State<'_, ServerManager>,
+) -> Result<ServerStatus, String> {
+    match manager.controller.lock().await.as_ref() {
+        Some(ctrl) => Ok(ctrl.status().await),
+        None => Ok(ServerStatus::default()),
     }
 }
 
@@ -49,6 +117,87 @@ pub fn get_local_ip() -> Result<String, String> {
         .map_err(|e| format!("Failed to get local IP: {}", e))
 }
 
+/// List the addresses of every network interface, IPv4 first, for choosing where to listen
+#[tauri::command]
+pub fn list_network_interfaces() -> Result<Vec<NetworkInterface>, String> {
+    list_interfaces().map_err(|e| e.to_string())
+}
+
+/// List every taken seat with its connection, lease, sync and download status
+#[tauri::command]
+pub async fn get_connected_clients(
+    manager: State<'_, ServerManager>,
+) -> Result<Vec<ClientSummary>, String> {
+    let guard = manager.controller.lock().await;
+    let ctrl = guard.as_ref().ok_or("Server is not running")?;
+    let clients = ctrl.perf_state.lock().await.client_summaries();
+    Ok(clients)
+}
+
+/// Remove a client from its seat. The client is told and goes back to the login page.
+#[tauri::command]
+pub async fn kick_client(
+    manager: State<'_, ServerManager>,
+    client_id: String,
+) -> Result<(), String> {
+    let guard = manager.controller.lock().await;
+    let ctrl = guard.as_ref().ok_or("Server is not running")?;
+    ctrl.kick_client(&client_id).await.map_err(|e| e.to_string())
+}
+
+/// Move a client to an empty seat, given as its index or a label like `C4`.
+/// The client fetches the new seat's files.
+#[tauri::command]
+pub async fn move_client(
+    manager: State<'_, ServerManager>,
+    client_id: String,
+    seat: String,
+) -> Result<(), String> {
+    let guard = manager.controller.lock().await;
+    let ctrl = guard.as_ref().ok_or("Server is not running")?;
+    ctrl.move_client(&client_id, &seat).await.map_err(|e| e.to_string())
+}
+
+/// Exchange the clients in two seats; one of them may be empty
+#[tauri::command]
+pub async fn swap_seats(
+    manager: State<'_, ServerManager>,
+    a: String,
+    b: String,
+) -> Result<(), String> {
+    let guard = manager.controller.lock().await;
+    let ctrl = guard.as_ref().ok_or("Server is not running")?;
+    ctrl.swap_seats(&a, &b).await.map_err(|e| e.to_string())
+}
+
+/// Join link and QR code (SVG and PNG) for the running server.
+/// With a `seat` (its index or a label like `C4`), the link pre-fills it and carries its ticket. `includeCode` adds the join code.
+#[tauri::command]
+pub async fn get_join_qr(
+    manager: State<'_, ServerManager>,
+    seat: Option<String>,
+    include_code: Option<bool>,
+) -> Result<JoinQr, String> {
+    let guard = manager.controller.lock().await;
+    let ctrl = guard.as_ref().ok_or("Server is not running")?;
+    ctrl.join_qr(seat.as_deref(), include_code.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Save a printable HTML page with one ticket QR code per seat
+#[tauri::command]
+pub async fn export_seat_qr_sheet(
+    manager: State<'_, ServerManager>,
+    path: String,
+) -> Result<(), String> {
+    let guard = manager.controller.lock().await;
+    let ctrl = guard.as_ref().ok_or("Server is not running")?;
+    ctrl.export_seat_sheet(Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Broadcast a JSON payload to all connected clients
 #[tauri::command]
 pub async fn broadcast_json(
@@ -56,63 +205,32 @@ pub async fn broadcast_json(
     message: Value,
 ) -> Result<(), String> {
     if let Some(controller) = manager.controller.lock().await.as_ref() {
-        broadcast_to_all(controller.perf_state.clone(), message).await;
+        broadcast_to_all(controller.perf_state.clone(), &message).await;
         Ok(())
     } else {
         Err("Server not running.".into())
     }
 }
 
+/// Schedule a phase on every client.
+/// Returns the lead time (ms) between now and the scheduled start.
 #[tauri::command]
 pub async fn broadcast_phase_start(
     manager: State<'_, ServerManager>,
-    app_state: State<'_, Arc<AppState>>,
     phase_id: String,
-  ) -> Result<(), String> {
-    // 1. Grab the phase out of AppState
-    let phases_map = app_state.phases.lock().await;
-    let phase = phases_map
-      .get(&phase_id)
-      .cloned()
-      .ok_or_else(|| format!("Phase `{}` not found", phase_id))?;
-    drop(phases_map);
-  
-    // 2. Turn Vec<SeatAssignment> → HashMap<seat_string, AssignmentPayload>
-    let assignments: HashMap<_, _> = phase
-      .assignments
-      .into_iter()
-      .enumerate()
-      .filter_map(|(i, sa)| {
-        match (sa.rnbo_id, sa.sheet_id) {
-          (Some(rnbo), Some(sheet)) => Some((
-            i.to_string(),
-            AssignmentPayload { rnbo_id: rnbo, sheet_id: sheet },
-          )),
-          _ => None,
-        }
-      })
-      .collect();
-  
-    // 3. Now timestamp in ms
-    let start_time = Utc::now().timestamp_millis();
-  
-    // 4. Build your strongly-typed payload
-    let payload = PhaseStartPayload {
-      msg_type: "phase_start",
-      bpm: phase.bpm,
-      count_in: phase.count_in,
-      start_time,
-      assignments,
-    };
-  
-    // 5. Broadcast via your WebSocket manager
+) -> Result<i64, String> {
     let guard = manager.controller.lock().await;
-    if let Some(ctrl) = guard.as_ref() {
-      // clone only the Arc-backed perf_state
-      broadcast_to_all(ctrl.perf_state.clone(), json!(payload)).await;
-      Ok(())
-    } else {
-      Err("Server is not running".into())
-    }
-  }
+    let ctrl = guard.as_ref().ok_or("Server is not running")?;
+    ctrl.start_phase(phase_id).await.map_err(|e| e.to_string())
+}
 
+/// Tell every client to stop the current phase on the same beat.
+/// Returns the scheduled stop time (server clock, ms).
+#[tauri::command]
+pub async fn broadcast_phase_stop(
+    manager: State<'_, ServerManager>,
+) -> Result<i64, String> {
+    let guard = manager.controller.lock().await;
+    let ctrl = guard.as_ref().ok_or("Server is not running")?;
+    Ok(ctrl.stop_phase().await)
+}
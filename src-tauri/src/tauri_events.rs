@@ -0,0 +1,12 @@
+use rtpc_core::events::EventSink;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+
+/// Forwards broker and session events to the admin UI
+pub struct TauriEvents(pub AppHandle);
+
+impl EventSink for TauriEvents {
+    fn emit(&self, event: &str, payload: Value) {
+        self.0.emit(event, payload).ok();
+    }
+}
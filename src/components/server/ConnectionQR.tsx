@@ -1,27 +1,42 @@
 import { useEffect, useState } from "react"
-import QRCode from "react-qr-code" // ✅ import the QR component
 import { invoke } from "@tauri-apps/api/core"
 import { Skeleton } from "@/components/ui/skeleton"
+import type { JoinQr } from "@/types/generated/JoinQr"
 
-export default function ConnectionQR({ port, serverOn }: { port: number, serverOn: boolean }) {
-  const [ip, setIp] = useState<string | null>(null)
+// The QR code and link are rendered by the backend from the interface the server advertises.
+// `joinUrl` only tells us when that changes.
+export default function ConnectionQR({ joinUrl, serverOn }: { joinUrl: string | null, serverOn: boolean }) {
+  const [qr, setQr] = useState<JoinQr | null>(null)
 
   useEffect(() => {
-    invoke<string>("get_local_ip")
-      .then(setIp)
-      .catch(err => console.error("Failed to get IP:", err))
-  }, [])
+    if (!serverOn || !joinUrl) {
+      setQr(null)
+      return
+    }
+    invoke<JoinQr>("get_join_qr")
+      .then(setQr)
+      .catch(err => console.error("Failed to get join QR code:", err))
+  }, [serverOn, joinUrl])
 
-  if (!serverOn || !ip) {
+  if (!serverOn || !qr) {
     return <Skeleton className="w-[256px] h-[256px] rounded-md" />
   }
 
-  const url = `https://${ip}:${port}`
-
   return (
     <div className="flex flex-col items-center">
-      <QRCode value={url} size={256} className="rounded-md bg-white p-2" />
-      <p className="mt-2 text-xs text-zinc-500">{url}</p>
+      <img
+        src={`data:image/svg+xml;utf8,${encodeURIComponent(qr.svg)}`}
+        alt={qr.url}
+        className="w-[256px] h-[256px] rounded-md bg-white"
+      />
+      <p className="mt-2 text-xs text-zinc-500">{qr.url}</p>
+      <a
+        href={`data:image/png;base64,${qr.png}`}
+        download="join-qr.png"
+        className="mt-1 text-xs text-zinc-400 underline hover:text-zinc-200"
+      >
+        Download PNG
+      </a>
     </div>
   )
 }
@@ -10,7 +10,15 @@ import {
   TooltipProvider,
   TooltipTrigger,
 } from "@/components/ui/tooltip"
-import { useState } from "react"
+import { useEffect, useState } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { toast } from "sonner"
+import type { ServerInfo } from "@/types/generated/ServerInfo"
+import type { Transport } from "@/types/generated/Transport"
+import type { SeatAllocation } from "@/types/generated/SeatAllocation"
+import type { SlowClientPolicy } from "@/types/generated/SlowClientPolicy"
+import type { NetworkInterface } from "@/types/generated/NetworkInterface"
 
 // Updated type to use a single port
 type ServerControlsProps = {
@@ -18,15 +26,92 @@ type ServerControlsProps = {
   setPort: (p: number) => void; // Single setter for the port
   serverOn: boolean;
   setServerOn: (val: boolean) => void;
+  setServerInfo?: (info: ServerInfo | null) => void;
 }
 
-export default function ServerControls({ port, setPort, serverOn, setServerOn }: ServerControlsProps) {
+// Optional port inputs: empty means unset
+const parsePort = (value: string) => (value === "" ? undefined : Number(value))
+
+const selectClassName =
+  "border-input flex h-9 w-full rounded-md border bg-transparent px-2 text-sm dark:bg-input/30 disabled:opacity-50"
+
+const interfaceLabel = (iface: NetworkInterface) => `${iface.name} – ${iface.address}`
+
+const laggingMessage = (seat: string, policy: SlowClientPolicy) => {
+  switch (policy) {
+    case "disconnect":
+      return `Seat ${seat} fell behind and was disconnected. It can rejoin.`
+    case "pause_files":
+      return `Seat ${seat} fell behind. Downloads to it are paused.`
+    case "warn":
+      return `Seat ${seat} is falling behind.`
+  }
+}
+
+export default function ServerControls({ port, setPort, serverOn, setServerOn, setServerInfo }: ServerControlsProps) {
   const [sessionTtlMinutes, setSessionTtlMinutes] = useState(60) // default 60 minutes
+  const [transport, setTransport] = useState<Transport>("tls")
+  const [plainPort, setPlainPort] = useState<number | undefined>(8080)
+  const [redirectPort, setRedirectPort] = useState<number | undefined>(undefined)
+  const [interfaces, setInterfaces] = useState<NetworkInterface[]>([])
+  // Empty means all interfaces / the default LAN address
+  const [bindAddress, setBindAddress] = useState("")
+  const [advertiseAddress, setAdvertiseAddress] = useState("")
+  // Advertise as <hostname>.local so phones can find the server without an IP
+  const [mdns, setMdns] = useState(true)
+  const [mdnsHostname, setMdnsHostname] = useState("rtpc")
+  const [joinByHostname, setJoinByHostname] = useState(false)
+  // Keep strangers on the same Wi-Fi out of the seats
+  const [joinCode, setJoinCode] = useState("")
+  const [requireSeatTokens, setRequireSeatTokens] = useState(false)
+  // Where clients joining with "any seat" end up
+  const [seatAllocation, setSeatAllocation] = useState<SeatAllocation>("in_order")
+  const [seatSeed, setSeatSeed] = useState<number | undefined>(undefined)
+  // Phones on bad Wi-Fi that stop reading their messages
+  const [slowClients, setSlowClients] = useState<SlowClientPolicy>("disconnect")
+
+  useEffect(() => {
+    if (!serverOn) return
+    const unlisten = listen<{ seat: string, policy: SlowClientPolicy }>("client-lagging", (event) => {
+      toast.warning(laggingMessage(event.payload.seat, event.payload.policy))
+    })
+    return () => {
+      unlisten.then(f => f())
+    }
+  }, [serverOn])
+
+  useEffect(() => {
+    invoke<NetworkInterface[]>("list_network_interfaces")
+      .then(setInterfaces)
+      .catch(err => console.error("Failed to list network interfaces:", err))
+  }, [])
   const [connecting, setConnecting] = useState(false)
   const { toggleServer } = useServerToggle()
 
   const handleToggle = async () => {
-    toggleServer({ port, ttlMinutes: sessionTtlMinutes, setServerOn, setConnecting }, serverOn)
+    toggleServer(
+      {
+        port,
+        ttlMinutes: sessionTtlMinutes,
+        transport,
+        plainPort,
+        redirectPort,
+        bindAddress: bindAddress || undefined,
+        advertiseAddress: bindAddress ? undefined : advertiseAddress || undefined,
+        mdns,
+        mdnsHostname: mdnsHostname || undefined,
+        joinByHostname,
+        joinCode,
+        requireSeatTokens,
+        seatAllocation,
+        seatSeed,
+        slowClients,
+        setServerOn,
+        setConnecting,
+        setServerInfo,
+      },
+      serverOn
+    )
   }
 
   return (
@@ -56,17 +141,50 @@ export default function ServerControls({ port, setPort, serverOn, setServerOn }:
             <Settings className="h-5 w-5" />
           </button>
         </PopoverTrigger>
-        <PopoverContent className="w-48 text-xs" align="end">
+        <PopoverContent className="w-64 text-xs" align="end">
           <div className="space-y-2">
             <div className="text-sm font-medium">Server Settings</div>
+            {/* Laptops often have Ethernet, Wi-Fi and VPN adapters; pick the one the audience is on */}
             <div className="space-y-1">
-              <label htmlFor="host" className="block text-xs text-zinc-400">Host</label>
-              <Input id="host" value="localhost" disabled />
+              <label htmlFor="host" className="block text-xs text-zinc-400">Interface</label>
+              <select
+                id="host"
+                value={bindAddress}
+                onChange={(e) => setBindAddress(e.target.value)}
+                disabled={serverOn || connecting}
+                className={selectClassName}
+              >
+                <option value="">All interfaces</option>
+                {interfaces.map((iface) => (
+                  <option key={`${iface.name}-${iface.address}`} value={iface.address}>
+                    {interfaceLabel(iface)}
+                  </option>
+                ))}
+              </select>
             </div>
+            {bindAddress === "" && (
+              <div className="space-y-1">
+                <label htmlFor="advertise" className="block text-xs text-zinc-400">Join Address</label>
+                <select
+                  id="advertise"
+                  value={advertiseAddress}
+                  onChange={(e) => setAdvertiseAddress(e.target.value)}
+                  disabled={serverOn || connecting}
+                  className={selectClassName}
+                >
+                  <option value="">Automatic</option>
+                  {interfaces.map((iface) => (
+                    <option key={`${iface.name}-${iface.address}`} value={iface.address}>
+                      {interfaceLabel(iface)}
+                    </option>
+                  ))}
+                </select>
+              </div>
+            )}
             {/* Single input for the shared server port */}
             <div className="space-y-1">
               {/* Updated label */}
-              <label htmlFor="server-port" className="block text-xs text-zinc-400">Server Port</label>
+              <label htmlFor="server-port" className="block text-xs text-zinc-400">Server Port (0 = any free port)</label>
               <TooltipProvider>
                 <Tooltip>
                   <TooltipTrigger asChild>
@@ -78,7 +196,7 @@ export default function ServerControls({ port, setPort, serverOn, setServerOn }:
                         onChange={(e) => setPort(Number(e.target.value))} // Use the single setter
                         disabled={serverOn || connecting}
                         className="w-full"
-                        min={1}
+                        min={0}
                         max={65535}
                       />
                     </div>
@@ -94,6 +212,152 @@ export default function ServerControls({ port, setPort, serverOn, setServerOn }:
             </div>
             {/* Removed the redundant "Site Port" input */}
 
+            <div className="space-y-1">
+              <label className="flex items-center gap-2 text-xs text-zinc-400">
+                <input
+                  type="checkbox"
+                  checked={mdns}
+                  onChange={(e) => setMdns(e.target.checked)}
+                  disabled={serverOn || connecting}
+                />
+                Advertise over mDNS
+              </label>
+              {mdns && (
+                <>
+                  <div className="flex items-center gap-1">
+                    <Input
+                      id="mdns-hostname"
+                      value={mdnsHostname}
+                      onChange={(e) => setMdnsHostname(e.target.value)}
+                      disabled={serverOn || connecting}
+                      className="w-full"
+                    />
+                    <span className="text-xs text-zinc-400">.local</span>
+                  </div>
+                  <label className="flex items-center gap-2 text-xs text-zinc-400">
+                    <input
+                      type="checkbox"
+                      checked={joinByHostname}
+                      onChange={(e) => setJoinByHostname(e.target.checked)}
+                      disabled={serverOn || connecting}
+                    />
+                    Use hostname in the join link
+                  </label>
+                </>
+              )}
+            </div>
+
+            <div className="space-y-1">
+              <label htmlFor="join-code" className="block text-xs text-zinc-400">Join Code (optional)</label>
+              <Input
+                id="join-code"
+                value={joinCode}
+                onChange={(e) => setJoinCode(e.target.value)}
+                disabled={serverOn || connecting}
+                placeholder="none"
+                className="w-full"
+              />
+              <label className="flex items-center gap-2 text-xs text-zinc-400">
+                <input
+                  type="checkbox"
+                  checked={requireSeatTokens}
+                  onChange={(e) => setRequireSeatTokens(e.target.checked)}
+                  disabled={serverOn || connecting}
+                />
+                Only admit seat QR tickets
+              </label>
+            </div>
+
+            <div className="space-y-1">
+              <label htmlFor="seat-allocation" className="block text-xs text-zinc-400">Open Seating</label>
+              <select
+                id="seat-allocation"
+                value={seatAllocation}
+                onChange={(e) => setSeatAllocation(e.target.value as SeatAllocation)}
+                disabled={serverOn || connecting}
+                className={selectClassName}
+              >
+                <option value="in_order">Fill in order</option>
+                <option value="spread">Spread across the grid</option>
+                <option value="random">Random</option>
+                <option value="assigned">Seats with parts first</option>
+              </select>
+              {seatAllocation === "random" && (
+                <Input
+                  id="seat-seed"
+                  type="number"
+                  value={seatSeed ?? ""}
+                  onChange={(e) => setSeatSeed(e.target.value === "" ? undefined : Number(e.target.value))}
+                  disabled={serverOn || connecting}
+                  placeholder="Seed (optional)"
+                  className="w-full"
+                  min={0}
+                />
+              )}
+            </div>
+
+            <div className="space-y-1">
+              <label htmlFor="slow-clients" className="block text-xs text-zinc-400">Slow Clients</label>
+              <select
+                id="slow-clients"
+                value={slowClients}
+                onChange={(e) => setSlowClients(e.target.value as SlowClientPolicy)}
+                disabled={serverOn || connecting}
+                className={selectClassName}
+              >
+                <option value="disconnect">Disconnect (they can rejoin)</option>
+                <option value="pause_files">Stop sending them files</option>
+                <option value="warn">Only warn me</option>
+              </select>
+            </div>
+
+            {/* Plain WS/HTTP is for localhost and wired rehearsal setups */}
+            <div className="space-y-1">
+              <label htmlFor="transport" className="block text-xs text-zinc-400">Transport</label>
+              <select
+                id="transport"
+                value={transport}
+                onChange={(e) => setTransport(e.target.value as Transport)}
+                disabled={serverOn || connecting}
+                className={selectClassName}
+              >
+                <option value="tls">TLS (wss/https)</option>
+                <option value="plain">Plain (ws/http)</option>
+                <option value="both">Both</option>
+              </select>
+            </div>
+            {transport === "both" && (
+              <div className="space-y-1">
+                <label htmlFor="plain-port" className="block text-xs text-zinc-400">Plain Port</label>
+                <Input
+                  id="plain-port"
+                  type="number"
+                  value={plainPort ?? ""}
+                  onChange={(e) => setPlainPort(parsePort(e.target.value))}
+                  disabled={serverOn || connecting}
+                  className="w-full"
+                  min={1}
+                  max={65535}
+                />
+              </div>
+            )}
+            {transport !== "plain" && (
+              <div className="space-y-1">
+                <label htmlFor="redirect-port" className="block text-xs text-zinc-400">HTTP Redirect Port (optional)</label>
+                <Input
+                  id="redirect-port"
+                  type="number"
+                  value={redirectPort ?? ""}
+                  onChange={(e) => setRedirectPort(parsePort(e.target.value))}
+                  disabled={serverOn || connecting}
+                  className="w-full"
+                  placeholder="off"
+                  min={1}
+                  max={65535}
+                />
+              </div>
+            )}
+
             {/* Session TTL input - remains the same */}
             <div className="space-y-1">
               <label htmlFor="TTL" className="block text-xs text-zinc-400">Session TTL (minutes)</label>
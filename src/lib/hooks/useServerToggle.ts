@@ -1,25 +1,59 @@
 import { invoke } from "@tauri-apps/api/core"
 import { toast } from "sonner"
+import type { ServerOptions } from "@/types/generated/ServerOptions"
+import type { ServerInfo } from "@/types/generated/ServerInfo"
+import type { Transport } from "@/types/generated/Transport"
+import type { SeatAllocation } from "@/types/generated/SeatAllocation"
+import type { SlowClientPolicy } from "@/types/generated/SlowClientPolicy"
 
 type UseServerToggleParams = {
   port: number
   ttlMinutes: number
+  transport?: Transport
+  plainPort?: number
+  redirectPort?: number
+  bindAddress?: string
+  advertiseAddress?: string
+  mdns?: boolean
+  mdnsHostname?: string
+  joinByHostname?: boolean
+  joinCode?: string
+  requireSeatTokens?: boolean
+  seatAllocation?: SeatAllocation
+  seatSeed?: number
+  slowClients?: SlowClientPolicy
   setServerOn: (val: boolean) => void
   setConnecting?: (val: boolean) => void
+  setServerInfo?: (info: ServerInfo | null) => void
 }
 
 export const useServerToggle = () => {
   const toggleServer = async ({
     port,
     ttlMinutes,
+    transport = "tls",
+    plainPort,
+    redirectPort,
+    bindAddress,
+    advertiseAddress,
+    mdns = true,
+    mdnsHostname,
+    joinByHostname = false,
+    joinCode,
+    requireSeatTokens = false,
+    seatAllocation = "in_order",
+    seatSeed,
+    slowClients = "disconnect",
     setServerOn,
     setConnecting,
+    setServerInfo,
   }: UseServerToggleParams, serverOn: boolean) => {
     if (serverOn) {
       try {
         await invoke("stop_server")
         toast("Server stopped.")
         setServerOn(false)
+        setServerInfo?.(null)
       } catch (err) {
         toast.error("Failed to stop server.")
         console.error(err)
@@ -28,14 +62,29 @@ export const useServerToggle = () => {
       setConnecting?.(true)
 
       try {
-        await invoke("start_server", {
+        const options: ServerOptions = {
           wsPort: port,
           ttlMs: ttlMinutes * 60_000,
-        })
+          transport,
+          plainPort: transport === "both" ? plainPort : undefined,
+          redirectPort: transport !== "plain" ? redirectPort : undefined,
+          bindAddress,
+          advertiseAddress,
+          mdns,
+          mdnsHostname: mdns ? mdnsHostname : undefined,
+          joinByHostname: mdns && joinByHostname,
+          joinCode: joinCode || undefined,
+          requireSeatTokens,
+          seatAllocation,
+          seatSeed: seatAllocation === "random" ? seatSeed : undefined,
+          slowClients,
+        }
+        const serverInfo = await invoke<ServerInfo>("start_server", { options })
         toast("Server started.")
         setServerOn(true)
+        setServerInfo?.(serverInfo)
       } catch (err) {
-        toast.error("Failed to start server.")
+        toast.error(`Failed to start server: ${err}`)
         console.error(err)
       } finally {
         setConnecting?.(false)
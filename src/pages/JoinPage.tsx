@@ -1,15 +1,30 @@
-import { useState } from "react"
-// import { invoke } from "@tauri-apps/api/core"
+import { useEffect, useState } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { save } from "@tauri-apps/plugin-dialog"
+import { toast } from "sonner"
 import ServerControls from "@/components/server/ServerControls"
 import ConnectionQR from "@/components/server/ConnectionQR"
 import { useNavigate } from "react-router-dom"
 import { useServerToggle } from "@/lib/hooks/useServerToggle"
 import { Button } from "@/components/ui/button"
+import type { ServerInfo } from "@/types/generated/ServerInfo"
+import type { ServerStatus } from "@/types/generated/ServerStatus"
+
+const STATUS_POLL_MS = 2000
+
+const formatUptime = (ms: number) => {
+  const seconds = Math.floor(ms / 1000)
+  const minutes = Math.floor(seconds / 60)
+  return `${Math.floor(minutes / 60)}h ${minutes % 60}m ${seconds % 60}s`
+}
 
 export default function JoinPage() {
   // Use a single state variable for the port
   const [port, setPort] = useState(3030) // Choose a default port
   const [serverOn, setServerOn] = useState(false)
+  const [serverInfo, setServerInfo] = useState<ServerInfo | null>(null)
+  const tlsInfo = serverInfo?.tls ?? null
+  const [status, setStatus] = useState<ServerStatus | null>(null)
   const navigate = useNavigate()
   const { toggleServer } = useServerToggle()
 
@@ -60,9 +75,42 @@ export default function JoinPage() {
   //   })
   // }
 
+  // Uptime and client count while the server is running
+  useEffect(() => {
+    if (!serverOn) {
+      setStatus(null)
+      return
+    }
+    const poll = () =>
+      invoke<ServerStatus>("get_server_status")
+        .then(setStatus)
+        .catch(err => console.error("Failed to get server status:", err))
+    poll()
+    const interval = setInterval(poll, STATUS_POLL_MS)
+    return () => clearInterval(interval)
+  }, [serverOn])
+
+  // One ticket QR code per seat, to print and tape to chairs.
+  // Tickets are signed with the session's seat secret, which the backend keeps in the session file.
+  const handleExportSheet = async () => {
+    const path = await save({
+      filters: [{ name: "HTML", extensions: ["html"] }],
+      defaultPath: "seat-qr-codes.html",
+    })
+    if (!path) return
+
+    try {
+      await invoke("export_seat_qr_sheet", { path })
+      toast("Seat sheet saved.")
+    } catch (err) {
+      toast.error(`Failed to export seat sheet: ${err}`)
+      console.error(err)
+    }
+  }
+
   const handleBack = async () => {
     if (serverOn) {
-      await toggleServer({ port, ttlMinutes: 60, setServerOn }, true) // Just to turn it off
+      await toggleServer({ port, ttlMinutes: 60, setServerOn, setServerInfo }, true) // Just to turn it off
     }
     navigate("/session")
   }
@@ -85,13 +133,41 @@ export default function JoinPage() {
           setPort={setPort}
           serverOn={serverOn}
           setServerOn={setServerOn}
+          setServerInfo={setServerInfo}
         />
       </div>
 
       {/* Center: QR code + button */}
       <div className="flex flex-col items-center justify-center h-full gap-4">
-        {/* Pass the single port to ConnectionQR */}
-        <ConnectionQR port={port} serverOn={serverOn} />
+        <ConnectionQR joinUrl={serverInfo?.join_url ?? null} serverOn={serverOn} />
+        {/* Self-signed certs trigger a browser warning; the audience can check this fingerprint */}
+        {serverOn && tlsInfo && (
+          <div className="max-w-md text-center text-xs text-zinc-400">
+            <div>{tlsInfo.self_signed ? "Self-signed certificate" : "Certificate"} SHA-256 fingerprint</div>
+            <div className="font-mono break-all">{tlsInfo.fingerprint}</div>
+          </div>
+        )}
+        {serverOn && serverInfo?.join_code && (
+          <div className="text-center">
+            <div className="text-xs text-zinc-400">Join code</div>
+            <div className="font-mono text-2xl tracking-widest">{serverInfo.join_code}</div>
+          </div>
+        )}
+        {status?.running && (
+          <div className="text-xs text-zinc-400">
+            Up {formatUptime(status.uptime_ms)} · {status.client_count} connected
+          </div>
+        )}
+        {serverOn && (
+          <Button
+            size="sm"
+            variant="secondary"
+            className="h-8 px-4 text-xs bg-zinc-700 hover:bg-zinc-600"
+            onClick={handleExportSheet}
+          >
+            Export seat QR codes
+          </Button>
+        )}
         {/* <button
           onClick={handleBroadcast}
           className="px-4 py-2 bg-primary text-white rounded-lg shadow hover:bg-primary/80 transition"
@@ -6,6 +6,7 @@ import {
 } from "@/components/ui/resizable";
 import { Button } from "@/components/ui/button";
 import { useNavigate } from "react-router-dom";
+import { toast } from "sonner";
 
 type Phase = {
   id: string
@@ -51,12 +52,21 @@ export default function PlayPage() {
 
   const handlePlayPhase = async (phaseId: string) => {
     try {
-      await invoke("broadcast_phase_start", { phaseId: phaseId });
+      const leadMs = await invoke<number>("broadcast_phase_start", { phaseId: phaseId });
+      toast(`Phase starts in ${leadMs} ms.`);
     } catch (err) {
       console.error("Failed to play phase:", err);
     }
   };
 
+  const handleStopPhase = async () => {
+    try {
+      await invoke("broadcast_phase_stop");
+    } catch (err) {
+      console.error("Failed to stop phase:", err);
+    }
+  };
+
   return (
     <div className="h-screen w-screen bg-background text-foreground">
       <ResizablePanelGroup
@@ -70,6 +80,14 @@ export default function PlayPage() {
               <h2 className="text-base font-semibold text-zinc-300">
                 Available Phases
               </h2>
+              <Button
+                size="sm"
+                variant="destructive"
+                className="ml-auto h-7 px-3 text-xs"
+                onClick={handleStopPhase}
+              >
+                Stop
+              </Button>
             </div>
 
             {/* Phase list */}
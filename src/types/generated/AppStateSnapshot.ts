@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Phase } from "./Phase";
+import type { RNBOPaletteItem } from "./RNBOPaletteItem";
+import type { SelectedFile } from "./SelectedFile";
+import type { SessionConfig } from "./SessionConfig";
+import type { SheetPaletteItem } from "./SheetPaletteItem";
+
+/**
+ * Everything in the current session, as shown by the admin UI
+ */
+export type AppStateSnapshot = { session: SessionConfig | null, selected_file: SelectedFile | null, rnbo_patches: Array<RNBOPaletteItem>, sheet_music: Array<SheetPaletteItem>, phases: { [key in string]?: Phase }, current_phase_id: string | null, };
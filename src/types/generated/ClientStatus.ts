@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How far a client has come through join → sync → download → ready
+ */
+export type ClientStatus = "joined" | "synced" | "downloading" | "ready";
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ClientStatus } from "./ClientStatus";
+
+/**
+ * Snapshot of one seat for the admin roster
+ */
+export type ClientSummary = { seat: string, client_id: string, connected: boolean, connected_at: number, disconnected_at: number | null, expires_at: number, last_message_at: number, status: ClientStatus, 
+/**
+ * Round trip of the latest time sync
+ */
+last_rtt_ms: number | null, 
+/**
+ * Fastest round trip kept, the one `offset_ms` is taken from
+ */
+best_rtt_ms: number | null, offset_ms: number | null, jitter_ms: number | null, skew_ppm: number | null, sync_samples: number, };
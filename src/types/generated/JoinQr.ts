@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A join link and its QR code, ready to show or save
+ */
+export type JoinQr = { url: string, svg: string, 
+/**
+ * PNG, base64-encoded
+ */
+png: string, };
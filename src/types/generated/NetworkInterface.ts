@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * One address of a network adapter, for choosing where to listen and what to put in the QR code
+ */
+export type NetworkInterface = { name: string, address: string, ipv6: boolean, loopback: boolean, };
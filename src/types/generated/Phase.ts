@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SeatAssignment } from "./SeatAssignment";
+
+export type Phase = { name: string, assignments: Array<SeatAssignment>, bpm: number, count_in: number, index: number, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How the server picks a seat for clients that join with `any`
+ */
+export type SeatAllocation = "in_order" | "spread" | "random" | "assigned";
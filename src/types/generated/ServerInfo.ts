@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TlsInfo } from "./TlsInfo";
+
+/**
+ * What a started server is serving
+ */
+export type ServerInfo = { 
+/**
+ * Address to put in join URLs
+ */
+address: string, 
+/**
+ * `.local` name advertised over mDNS, if advertising
+ */
+hostname: string | null, 
+/**
+ * Where the audience opens the client: HTTPS when it's served, otherwise plain HTTP
+ */
+join_url: string, 
+/**
+ * Address listened on, `0.0.0.0` for all interfaces
+ */
+bind_address: string, tls_port: number | null, plain_port: number | null, redirect_port: number | null, tls: TlsInfo | null, 
+/**
+ * Code the audience has to enter, shown next to the QR code
+ */
+join_code: string | null, require_seat_tokens: boolean, };
@@ -0,0 +1,66 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SeatAllocation } from "./SeatAllocation";
+import type { SlowClientPolicy } from "./SlowClientPolicy";
+import type { Transport } from "./Transport";
+
+/**
+ * How to run the broker, as sent by the admin UI
+ */
+export type ServerOptions = { 
+/**
+ * 0 picks a free port, which `start_server` reports back
+ */
+wsPort: number, ttlMs: number, safetyMs?: number, resyncIntervalMs?: number, 
+/**
+ * PEM certificate and key. Without them a self-signed certificate is used.
+ */
+certPath?: string, keyPath?: string, transport: Transport, 
+/**
+ * Plain WS/HTTP port when `transport` is `both`
+ */
+plainPort?: number, 
+/**
+ * Answer plain HTTP on this port with a redirect to HTTPS
+ */
+redirectPort?: number, 
+/**
+ * IP of the interface to listen on (IPv4 or IPv6). All IPv4 interfaces if unset.
+ */
+bindAddress?: string, 
+/**
+ * IP to put in the join URL. Defaults to the bound address, or the main LAN address
+ * when listening on all interfaces.
+ */
+advertiseAddress?: string, 
+/**
+ * Advertise the server over mDNS (on by default)
+ */
+mdns?: boolean, 
+/**
+ * mDNS host label, giving `<label>.local`. Defaults to `rtpc`.
+ */
+mdnsHostname?: string, 
+/**
+ * Put the `.local` hostname in the join URL and QR codes instead of the IP
+ */
+joinByHostname?: boolean, 
+/**
+ * Code the audience has to enter to take a seat
+ */
+joinCode?: string, 
+/**
+ * Only admit holders of a seat's QR ticket
+ */
+requireSeatTokens?: boolean, 
+/**
+ * How seats are picked for clients that join with `any`
+ */
+seatAllocation?: SeatAllocation, 
+/**
+ * Seed for `random` seat allocation, to repeat a run. Random if unset.
+ */
+seatSeed?: number, 
+/**
+ * What happens to clients that fall too far behind on their messages (disconnect by default)
+ */
+slowClients?: SlowClientPolicy, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ServerInfo } from "./ServerInfo";
+
+/**
+ * Snapshot for the admin UI's status display
+ */
+export type ServerStatus = { running: boolean, 
+/**
+ * Address clients reach the server on
+ */
+address: string | null, info: ServerInfo | null, uptime_ms: number, client_count: number, };
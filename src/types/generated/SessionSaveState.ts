@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Phase } from "./Phase";
+import type { RNBOPaletteItem } from "./RNBOPaletteItem";
+import type { SelectedFile } from "./SelectedFile";
+import type { SessionConfig } from "./SessionConfig";
+import type { SheetPaletteItem } from "./SheetPaletteItem";
+
+export type SessionSaveState = { config: SessionConfig, selected_file: SelectedFile | null, rnbo_patches: Array<RNBOPaletteItem>, sheet_music: Array<SheetPaletteItem>, phases: { [key in string]?: Phase }, current_phase_id: string | null, 
+/**
+ * Key for signing seat tickets, kept so printed QR codes stay valid across restarts
+ */
+seat_secret?: string, };
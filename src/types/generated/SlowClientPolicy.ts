@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What happens to a client that falls too far behind on its messages
+ */
+export type SlowClientPolicy = "disconnect" | "pause_files" | "warn";
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What the admin UI shows about the certificate in use
+ */
+export type TlsInfo = { cert_path: string, key_path: string, 
+/**
+ * SHA-256 of the certificate, as browsers show it (AB:CD:...)
+ */
+fingerprint: string, self_signed: boolean, };